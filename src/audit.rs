@@ -0,0 +1,136 @@
+//! Strength and breach checks for stored password entries (`clipm audit-passwords`).
+//!
+//! Breach checks use the Have I Been Pwned range API: only the first five hex
+//! characters of the password's SHA-1 leave the machine, and the match against
+//! the returned suffixes happens locally.
+
+use sha1::{Digest, Sha1};
+use std::process::Command;
+
+use crate::i18n::t;
+use crate::models::ClipmError;
+
+/// Passwords shorter than this are reported as weak.
+pub const MIN_LENGTH: usize = 12;
+/// Passwords with a lower estimated entropy are reported as weak.
+pub const MIN_ENTROPY_BITS: f64 = 60.0;
+
+const RANGE_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strength {
+    /// Length in characters
+    pub length: usize,
+    /// Brute-force entropy: length × log2 of the character pool the password
+    /// draws from. A run of three or more repeated or consecutive characters
+    /// (`aaa`, `abcd`, `4321`) only counts its first two. An upper bound;
+    /// dictionary words score higher than they are.
+    pub entropy_bits: f64,
+}
+
+impl Strength {
+    pub fn of(password: &str) -> Strength {
+        let has = |f: fn(&char) -> bool| password.chars().any(|c| f(&c));
+        let mut pool = 0u32;
+        if has(char::is_ascii_lowercase) {
+            pool += 26;
+        }
+        if has(char::is_ascii_uppercase) {
+            pool += 26;
+        }
+        if has(char::is_ascii_digit) {
+            pool += 10;
+        }
+        if has(|c| c.is_ascii_punctuation() || *c == ' ') {
+            pool += 33;
+        }
+        if has(|c| !c.is_ascii()) {
+            pool += 100;
+        }
+        let length = password.chars().count();
+        let entropy_bits = if pool == 0 { 0.0 } else { unpredictable_chars(password) as f64 * f64::from(pool).log2() };
+        Strength { length, entropy_bits }
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.length < MIN_LENGTH || self.entropy_bits < MIN_ENTROPY_BITS
+    }
+}
+
+/// Characters not continuing a run: a character that steps from the
+/// previous one by the same 0 or ±1 as that one did from its predecessor
+/// (the third `a` of `aaa`, the `c` of `abc`) adds nothing an attacker
+/// trying runs wouldn't guess.
+fn unpredictable_chars(password: &str) -> usize {
+    let codes: Vec<i64> = password.chars().map(|c| i64::from(u32::from(c))).collect();
+    let runs = codes.windows(3).filter(|w| {
+        let step = w[1] - w[0];
+        step.abs() <= 1 && w[2] - w[1] == step
+    });
+    codes.len() - runs.count()
+}
+
+/// Uppercase hex SHA-1, the form the range API uses.
+fn sha1_hex(password: &str) -> String {
+    Sha1::digest(password.as_bytes()).iter().map(|b| format!("{b:02X}")).collect()
+}
+
+/// Look up `suffix` in a range API response (`SUFFIX:COUNT` per line).
+/// Padding entries have a count of 0.
+fn count_in_range(body: &str, suffix: &str) -> u64 {
+    body.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(s, _)| s.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// How many times the password appears in known breaches (0 if none). Uses
+/// `curl`, with response padding so the reply size doesn't leak the prefix.
+pub fn breach_count(password: &str) -> Result<u64, ClipmError> {
+    let hash = sha1_hex(password);
+    let (prefix, suffix) = hash.split_at(5);
+    let output = Command::new("curl")
+        .args(["-sSf", "--max-time", "10", "-H", "Add-Padding: true"])
+        .arg(format!("{RANGE_URL}{prefix}"))
+        .output()
+        .map_err(|e| ClipmError::Io(t!("Cannot run `curl` for the breach check: {error}", error = e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(ClipmError::Io(t!("Breach check failed: {error}", error = stderr)));
+    }
+    Ok(count_in_range(&String::from_utf8_lossy(&output.stdout), suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strength() {
+        let s = Strength::of("password");
+        assert_eq!(s.length, 8);
+        assert!((s.entropy_bits - 8.0 * 26f64.log2()).abs() < 1e-9);
+        assert!(s.is_weak());
+
+        assert!(Strength::of("abcdefghijklmnop").is_weak());
+        assert!(Strength::of("Zzzzzzzzzzzzzzzz9!").is_weak());
+        assert_eq!(unpredictable_chars("xabcba11119"), 7);
+        assert!(!Strength::of("Tr0ub4dor&3-horse").is_weak());
+        assert_eq!(Strength::of("").entropy_bits, 0.0);
+        assert_eq!(Strength::of("пароль").length, 6);
+    }
+
+    #[test]
+    fn test_sha1_hex() {
+        assert_eq!(sha1_hex("password"), "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8");
+    }
+
+    #[test]
+    fn test_count_in_range() {
+        let body = "003D68EB55068C33ACE09247EE4C639306B:3\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\r\n011053FD0102E94D6AE2F8B83D76FAF94F6:0\r\n";
+        assert_eq!(count_in_range(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 9545824);
+        assert_eq!(count_in_range(body, "011053FD0102E94D6AE2F8B83D76FAF94F6"), 0);
+        assert_eq!(count_in_range(body, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), 0);
+    }
+}
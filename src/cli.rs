@@ -1,33 +1,223 @@
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+use clipm::clipboard::Selection;
+use clipm::export::ExportFormat;
+use clipm::generate::{Charset, Generator};
+use clipm::models::{ContentType, EntryRef, Flavor, ListSort, OutputFormat, SortOrder, SourcedRef};
+use clipm::normalize::Case;
 
 #[derive(Parser)]
 #[command(name = "clipm", version, about = "CLI clipboard manager for macOS")]
 pub struct Cli {
+    /// Assume "yes" for every confirmation prompt
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+    /// Use this profile's history instead of the one `[[workspaces]]` picks
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// What `clipm __complete` lists.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CompleteKind {
+    Labels,
+    Ids,
+}
+
+#[derive(Subcommand)]
+pub enum SessionAction {
+    /// Start a new session (ending the running one)
+    Start {
+        /// Optional name, e.g. "debugging prod"
+        name: Option<String>,
+    },
+    /// End the running session
+    End,
+    /// Show recent sessions with their entry counts
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum SnippetAction {
+    /// Save a template, e.g. "Hi {{name}}, ..."
+    Add {
+        /// Name to use it by
+        name: String,
+        /// Template text [default: the text on the clipboard]
+        content: Option<String>,
+        /// Label, shared with history entries (`tree`, label completion)
+        #[arg(short, long)]
+        label: Option<String>,
+    },
+    /// Show snippets with the variables they need
+    List {
+        /// Fuzzy search in names, labels and content
+        query: Option<String>,
+        /// Only snippets with this label or a label below it (work/projA)
+        #[arg(short = 'L', long)]
+        label: Option<String>,
+    },
+    /// Fill in a snippet's placeholders and copy the result
+    Use {
+        name: String,
+        /// Value for a placeholder, e.g. --var name=Ivan (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = clipm::template::parse_var)]
+        vars: Vec<(String, String)>,
+        /// Print the result instead of copying it
+        #[arg(short, long)]
+        stdout: bool,
+    },
+    /// Delete a snippet
+    Delete {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// Show deleted entries, most recently deleted first
+    List,
+    /// Permanently delete everything in the trash
+    Empty,
+}
+
+#[derive(Subcommand)]
+pub enum RulesAction {
+    /// Show which rules would fire on some text and what `store` would save
+    Test {
+        /// Text to try the rules on
+        #[arg(long, conflicts_with = "id", required_unless_present = "id")]
+        text: Option<String>,
+        /// Try them on this entry's content instead (ID or @N)
+        #[arg(long)]
+        id: Option<EntryRef>,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum Command {
+    /// Create the data directory, database and a default config file
+    Init {
+        /// Also install launchd agents for a daily `clipm prune` and `[snapshot]` capture
+        #[arg(long)]
+        launchd: bool,
+        /// Encrypt the database with a passphrase (kept in the Keychain)
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Save the encrypted history's passphrase in the Keychain
+    Unlock,
+    /// Remove the encrypted history's passphrase from the Keychain
+    Lock,
+    /// Re-encrypt the history with a new key or passphrase
+    Rekey,
+    /// Show which profile's history commands use in this directory
+    Profile,
     /// Save current clipboard to history
     Store {
         /// Optional label for the entry
         #[arg(short, long)]
         label: Option<String>,
-        /// Content type: text or password
-        #[arg(short = 't', long = "type", default_value = "text")]
-        content_type: String,
+        /// Content type [default: detected; text, url, json or code]
+        #[arg(short = 't', long = "type", value_enum)]
+        content_type: Option<ContentType>,
+        /// Ask for a label after storing (default from `store.prompt_label` in config)
+        #[arg(short, long)]
+        prompt_label: bool,
+        /// Print only the new entry's ID (for `id=$(clipm store --porcelain)`)
+        #[arg(short, long, visible_alias = "porcelain")]
+        quiet: bool,
+        /// Store what is piped on stdin instead of the clipboard (PNG data with --type image)
+        #[arg(long)]
+        stdin: bool,
+        /// Skip content that is anywhere in history, not just the latest entry (default from `store.unique` in config)
+        #[arg(short, long)]
+        unique: bool,
+        /// Which selection to read: the clipboard (default) or the primary selection (highlighted text)
+        #[cfg(all(target_os = "linux", feature = "primary-selection"))]
+        #[arg(long, value_enum)]
+        selection: Option<Selection>,
     },
     /// Copy entry to clipboard (default: most recent)
     Get {
-        /// Entry ID (defaults to most recent)
-        id: Option<i64>,
+        /// Entry ID or @N for the Nth most recent (defaults to most recent); t:17 is a team entry
+        id: Option<SourcedRef>,
+        /// Take the entry from the read-only `[team]` database
+        #[arg(long, conflicts_with = "delete")]
+        team: bool,
+        /// Replace $VAR and ${VAR} with environment values before copying
+        #[arg(long)]
+        expand_env: bool,
+        /// Expand {{date}}, {{time}}, {{counter:NAME}} placeholders before copying
+        #[arg(short = 'x', long)]
+        expand: bool,
+        /// Remove utm_*, fbclid and other tracking parameters from a copied URL
+        #[arg(long)]
+        clean_url: bool,
+        /// Turn smart quotes and dashes into ASCII and normalize Unicode to NFC
+        #[arg(long)]
+        ascii: bool,
+        /// Respell identifiers, e.g. userId -> user_id with --case snake
+        #[arg(long, value_enum)]
+        case: Option<Case>,
+        /// Copy only the plain text, without the formatting stored with it
+        #[arg(long, conflicts_with_all = ["format", "stdout"])]
+        plain: bool,
+        /// Copy this flavor: text drops the formatting, html fails without it [default: all the entry has]; with --stdout, print it
+        #[arg(long, value_enum, conflicts_with_all = ["format", "plain"])]
+        flavor: Option<Flavor>,
+        /// Which selection to copy to: the clipboard (default) or the primary selection (middle-click paste)
+        #[cfg(all(target_os = "linux", feature = "primary-selection"))]
+        #[arg(long, value_enum)]
+        selection: Option<Selection>,
+        /// Remove the entry from history after copying (for one-time codes)
+        #[arg(short, long)]
+        delete: bool,
+        /// Read the clipboard back after copying and fail if it doesn't match
+        #[arg(long)]
+        verify: bool,
+        /// Print the entry in this format instead of copying it
+        #[arg(short, long, value_enum, conflicts_with = "stdout")]
+        format: Option<OutputFormat>,
+        /// Write the raw content to stdout instead of copying it (PNG data for images)
+        #[arg(short, long, conflicts_with = "verify")]
+        stdout: bool,
+        /// Allow --stdout to print password entries
+        #[arg(long, requires = "stdout")]
+        reveal: bool,
+        /// After copying, paste into the frontmost app with ⌘V (via osascript)
+        #[arg(long, conflicts_with_all = ["format", "stdout"])]
+        paste_via_applescript: bool,
+        /// Bring this app to the front before pasting
+        #[arg(long, value_name = "NAME", requires = "paste_via_applescript")]
+        app: Option<String>,
+        /// Copy through the terminal with an OSC 52 escape sequence, e.g. to
+        /// the local clipboard from an SSH session
+        #[arg(long, conflicts_with_all = ["format", "stdout", "paste_via_applescript"])]
+        osc52: bool,
+    },
+    /// Copy an entry and remove it from history (default: most recent)
+    Pop {
+        /// Entry ID or @N for the Nth most recent (defaults to most recent)
+        id: Option<EntryRef>,
+        /// Write the raw content to stdout instead of copying it
+        #[arg(short, long)]
+        stdout: bool,
+        /// Allow --stdout to print password entries
+        #[arg(long, requires = "stdout")]
+        reveal: bool,
     },
     /// Show clipboard history as a table
     List {
-        /// Maximum number of entries to show
-        #[arg(short, long, default_value = "20")]
-        limit: usize,
+        /// Maximum number of entries to show [default: 20, or `limit` in config]
+        #[arg(short, long)]
+        limit: Option<usize>,
         /// Number of entries to skip
         #[arg(short, long, default_value = "0")]
         offset: usize,
@@ -37,40 +227,348 @@ pub enum Command {
         /// Filter to entries from the last N days
         #[arg(short, long)]
         days: Option<u32>,
-        /// Filter by content type: text or password
-        #[arg(short = 't', long = "type")]
-        content_type: Option<String>,
+        /// Only entries stored at or after this time: 2h, 7d, 2024-06-01 or 2024-06-01T09:30
+        #[arg(long, value_parser = clipm::timeparse::parse_since)]
+        since: Option<DateTime<Utc>>,
+        /// Only entries stored before this time; a bare date includes that whole day
+        #[arg(long, value_parser = clipm::timeparse::parse_until)]
+        until: Option<DateTime<Utc>>,
+        /// Filter by content type
+        #[arg(short = 't', long = "type", value_enum)]
+        content_type: Option<ContentType>,
+        /// Output format [default: table, or `format` in config]
+        #[arg(short, long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Only entries stored in this directory or below (needs `capture_env` in config)
+        #[arg(long, value_name = "DIR")]
+        cwd: Option<String>,
+        /// Only entries stored on this git branch (needs `capture_env` in config)
+        #[arg(long)]
+        branch: Option<String>,
+        /// Only entries from this session: "current", a session ID or name
+        #[arg(long)]
+        session: Option<String>,
+        /// Only pinned entries
+        #[arg(short, long)]
+        pinned: bool,
+        /// Sort by this field [default: created, or `sort` in config]
+        #[arg(long, value_enum)]
+        sort: Option<ListSort>,
+        /// Sort direction [default: asc for label, desc otherwise]
+        #[arg(long, value_enum)]
+        order: Option<SortOrder>,
+        /// Print only entry IDs, one per line
+        #[arg(long)]
+        ids_only: bool,
+        /// Print only entry IDs, NUL-delimited (for `xargs -0`)
+        #[arg(short = '0', long)]
+        null: bool,
     },
-    /// Full-text search clipboard history
+    /// Full-text search clipboard history (not snippets: see `snippet list <QUERY>`)
     Search {
         /// Search query
         query: String,
-        /// Maximum number of results
-        #[arg(short, long, default_value = "20")]
-        limit: usize,
+        /// Match characters in order anywhere (`clipb` finds "clipboard") instead of whole words
+        #[arg(short = 'z', long)]
+        fuzzy: bool,
+        /// Treat the query as a regular expression (e.g. 'AKIA[0-9A-Z]{16}')
+        #[arg(short = 'E', long, conflicts_with = "fuzzy")]
+        regex: bool,
+        /// Maximum number of results [default: 20, or `limit` in config]
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Number of results to skip
+        #[arg(short, long, default_value = "0")]
+        offset: usize,
+        /// Only search entries with this label or a label below it (work/projA)
+        #[arg(short = 'L', long)]
+        label: Option<String>,
         /// Filter to entries from the last N days
         #[arg(short, long)]
         days: Option<u32>,
-        /// Filter by content type: text or password
-        #[arg(short = 't', long = "type")]
-        content_type: Option<String>,
+        /// Only entries stored at or after this time: 2h, 7d, 2024-06-01 or 2024-06-01T09:30
+        #[arg(long, value_parser = clipm::timeparse::parse_since)]
+        since: Option<DateTime<Utc>>,
+        /// Only entries stored before this time; a bare date includes that whole day
+        #[arg(long, value_parser = clipm::timeparse::parse_until)]
+        until: Option<DateTime<Utc>>,
+        /// Filter by content type
+        #[arg(short = 't', long = "type", value_enum)]
+        content_type: Option<ContentType>,
+        /// Output format [default: table, or `format` in config]
+        #[arg(short, long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Show one day's entries in order, grouped by hour
+    Timeline {
+        /// Day to show, as YYYY-MM-DD [default: today]
+        #[arg(short, long)]
+        day: Option<chrono::NaiveDate>,
     },
+    /// Live view of capture rate, latest entries, database size and retention
+    Top {
+        /// Seconds between refreshes
+        #[arg(short = 'n', long, default_value = "2")]
+        interval: u64,
+        /// Print one snapshot and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Group entries into work sessions
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+    /// Reusable templates with {{placeholders}}, kept apart from history
+    Snippet {
+        #[command(subcommand)]
+        action: SnippetAction,
+    },
+    /// Show the last 10 entries and copy one with a single keypress
+    Recent,
+    /// Show the label hierarchy (labels split on '/') with entry counts
+    Tree,
     /// Add or update a label on an existing entry
     Label {
-        /// Entry ID
-        id: i64,
+        /// Entry ID or @N for the Nth most recent
+        #[arg(required_unless_present = "last")]
+        id: Option<EntryRef>,
         /// Label text (omit to remove label)
         label: Option<String>,
+        /// Label the most recent entry instead (no value removes its label)
+        #[arg(long, value_name = "LABEL", num_args = 0..=1, default_missing_value = "", conflicts_with_all = ["id", "label"])]
+        last: Option<String>,
+    },
+    /// Set or remove an entry's title, shown in listings instead of its preview
+    Title {
+        /// Entry ID or @N for the Nth most recent
+        id: EntryRef,
+        /// Title text, e.g. "prod DB connection string" (omit to remove the title)
+        title: Option<String>,
+    },
+    /// Set or remove the text-expansion trigger of an entry, e.g. ";addr"
+    Trigger {
+        /// Entry ID or @N for the Nth most recent
+        id: EntryRef,
+        /// Trigger text (omit to remove the trigger)
+        trigger: Option<String>,
+    },
+    /// List entries that have a trigger
+    Triggers {
+        /// Output format
+        #[arg(short, long, value_enum)]
+        format: Option<OutputFormat>,
     },
-    /// Delete a single entry
+    /// Copy the entry with this trigger, filling in {{date}} and other placeholders
+    Expand {
+        /// Trigger text, e.g. ";addr"
+        trigger: String,
+        /// Write the content to stdout instead of copying it
+        #[arg(short, long)]
+        stdout: bool,
+        /// Allow --stdout to print password entries
+        #[arg(long, requires = "stdout")]
+        reveal: bool,
+    },
+    /// Write history to a JSON, CSV or snippets file, e.g. for backups or another machine
+    Export {
+        /// File format
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only entries matching this full-text query (same syntax as `search`)
+        #[arg(short, long)]
+        query: Option<String>,
+        /// Only entries with this label
+        #[arg(short = 'L', long)]
+        label: Option<String>,
+        /// Only entries from the last N days
+        #[arg(short, long)]
+        days: Option<u32>,
+        /// Only entries of this content type
+        #[arg(short = 't', long = "type", value_enum)]
+        content_type: Option<ContentType>,
+        /// Leave out password entries
+        #[arg(long)]
+        no_passwords: bool,
+        /// age-encrypt the export for this recipient (age1… or ssh- key, or a recipients file)
+        #[arg(long, value_name = "RECIPIENT")]
+        encrypt: Option<String>,
+    },
+    /// Add entries from a JSON export, skipping ones already in history
+    Import {
+        /// File written by `clipm export` (may be age-encrypted)
+        file: PathBuf,
+        /// Show what would be imported without changing history
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// age identity for an encrypted export [default: `identity` in [encryption]]
+        #[arg(short, long)]
+        identity: Option<PathBuf>,
+    },
+    /// Check a JSON export against history and report what differs
+    Verify {
+        /// File written by `clipm export` (may be age-encrypted)
+        file: PathBuf,
+        /// age identity for an encrypted export [default: `identity` in [encryption]]
+        #[arg(short, long)]
+        identity: Option<PathBuf>,
+        /// Leave password entries in history out, for backups made with --no-passwords
+        #[arg(long)]
+        no_passwords: bool,
+    },
+    /// Move one or more entries to the trash
     Delete {
-        /// Entry ID to delete
-        id: i64,
+        /// Entry IDs (or @N for the Nth most recent) to delete
+        #[arg(required = true)]
+        ids: Vec<EntryRef>,
+    },
+    /// Join several entries into a new one, in the order given
+    Merge {
+        /// Entry IDs (or @N for the Nth most recent) to join
+        #[arg(required = true, num_args = 2..)]
+        ids: Vec<EntryRef>,
+        /// Text put between entries (\n and \t are understood)
+        #[arg(short, long, default_value = "\\n")]
+        separator: String,
+        /// Move the merged entries to the trash
+        #[arg(short, long)]
+        delete: bool,
     },
-    /// Clear all clipboard history
+    /// Deleted entries, kept for `trash_days` in config
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+    /// Move entries back from the trash
+    Restore {
+        /// Entry IDs as shown by `clipm trash list`
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+    /// Restore the entries removed by the last `delete` or `clear`
+    Undo,
+    /// Fix up an entry's content in $VISUAL or $EDITOR
+    Edit {
+        /// Entry ID or @N for the Nth most recent
+        id: EntryRef,
+    },
+    /// Change an entry's type, e.g. mark text you stored as a password
+    Convert {
+        /// Entry ID or @N for the Nth most recent
+        id: EntryRef,
+        /// New type (any but image)
+        #[arg(long, value_enum)]
+        to: ContentType,
+    },
+    /// Re-detect the type (text, url, json, code) of existing entries
+    Reclassify {
+        /// Show what would change without changing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Remove repeated content anywhere in history, keeping the newest copy
+    Dedupe {
+        /// Show what would be removed without removing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Copy a freshly generated UUID, random token or test data
+    Gen {
+        /// uuid, hex:N or base64:N (N random bytes, default 32), lorem, name or email
+        #[arg(value_name = "KIND")]
+        generator: Generator,
+        /// Number of lorem ipsum paragraphs
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=clipm::generate::MAX_PARAGRAPHS as i64))]
+        paragraphs: Option<u8>,
+        /// Also store the value in history
+        #[arg(long)]
+        store: bool,
+        /// Label for the stored entry
+        #[arg(short, long, requires = "store")]
+        label: Option<String>,
+    },
+    /// Generate a password, store it as a password entry and copy it
+    Generate {
+        /// Number of characters
+        #[arg(long, default_value = "24", value_parser = clap::value_parser!(u16).range(4..=1024))]
+        length: u16,
+        /// Characters to draw from
+        #[arg(long, value_enum, default_value = "alnum-symbols")]
+        charset: Charset,
+        /// Label for the entry [default: password]
+        #[arg(short, long)]
+        label: Option<String>,
+        /// Clear the clipboard this many seconds after copying, unless something else was copied since (0 = never)
+        #[arg(long, value_name = "SECONDS", default_value = "45")]
+        clear_after: u64,
+    },
+    /// Debug the `[[rules]]` in the config file
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+    /// Rebuild the full-text search index, e.g. after a tokenizer change or corruption
+    Reindex {
+        /// Recreate the index with this FTS5 tokenizer, e.g. "porter unicode61" or "trigram"
+        #[arg(long, value_name = "SPEC")]
+        tokenizer: Option<String>,
+    },
+    /// Pin entries so `clear`, `prune` and other retention rules keep them
+    Pin {
+        /// Entry IDs (or @N for the Nth most recent) to pin
+        #[arg(required = true)]
+        ids: Vec<EntryRef>,
+    },
+    /// Unpin entries
+    Unpin {
+        /// Entry IDs (or @N for the Nth most recent) to unpin
+        #[arg(required = true)]
+        ids: Vec<EntryRef>,
+    },
+    /// Report weak or breached secrets among password entries
+    AuditPasswords {
+        /// Also check Have I Been Pwned (sends only the first 5 characters of each SHA-1)
+        #[arg(long)]
+        breaches: bool,
+    },
+    /// Delete entries outside the retention limits in config
+    Prune {
+        /// Show what would be deleted without deleting anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Delete entries older than N days [default: `max_age_days` in config]
+        #[arg(long, value_name = "DAYS")]
+        keep_days: Option<u32>,
+        /// Keep only the newest N entries [default: `max_entries` in config]
+        #[arg(long, value_name = "N")]
+        keep_max: Option<usize>,
+    },
+    /// Move all unpinned entries to the trash
     Clear {
         /// Skip confirmation prompt
         #[arg(short, long)]
         force: bool,
     },
+    /// Run a clipm:// URL, e.g. from an app registered for the scheme
+    UrlHandler {
+        /// URL such as clipm://get?id=42&x-success=shortcuts://
+        url: String,
+    },
+    /// Print a shell completion script
+    Completions {
+        shell: Shell,
+    },
+    /// Clear the clipboard after a delay if it still holds the text hashed on stdin (started by `generate`)
+    #[command(name = "__clear-clipboard", hide = true)]
+    ClearClipboard {
+        seconds: u64,
+    },
+    /// Values for the completion scripts, one "value<TAB>description" per line
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        kind: CompleteKind,
+    },
 }
@@ -1,17 +1,668 @@
-use arboard::Clipboard;
-use crate::models::ClipmError;
+use arboard::{Clipboard, ImageData};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
 
-pub fn read_text() -> Result<String, ClipmError> {
-    let mut cb = Clipboard::new()?;
-    let text = cb.get_text()?;
+use crate::config::ClipboardConfig;
+use crate::i18n::t;
+use crate::models::{ClipImage, ClipmError, SecretString};
+
+/// Writes tried by `write_text_verified` before giving up.
+const VERIFY_ATTEMPTS: u32 = 2;
+/// Time given to other clipboard owners to interfere before reading back.
+const VERIFY_DELAY: Duration = Duration::from_millis(50);
+
+/// Environment variable selecting the clipboard backend when
+/// `ClipboardOverrides` doesn't.
+pub const BACKEND_ENV: &str = "CLIPM_CLIPBOARD";
+
+/// Environment variable naming the Linux selection the system backend uses
+/// when `--selection` isn't given.
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+pub const SELECTION_ENV: &str = "CLIPM_SELECTION";
+
+/// A backend used instead of `copy_cmd`/`paste_cmd` and the system clipboard.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    /// Process-local, for tests and headless CI
+    Memory,
+    /// A plain file standing in for the clipboard
+    File(PathBuf),
+    /// The terminal's clipboard, through OSC 52 (write-only)
+    Osc52,
+}
+
+impl Backend {
+    /// The backend `BACKEND_ENV` names: `memory`, `file:/path` or `osc52`.
+    fn from_env() -> Option<Backend> {
+        let value = std::env::var(BACKEND_ENV).ok()?;
+        match value.as_str() {
+            "memory" => Some(Backend::Memory),
+            "osc52" => Some(Backend::Osc52),
+            _ => value.strip_prefix("file:").map(|path| Backend::File(PathBuf::from(path))),
+        }
+    }
+}
+
+/// Clipboard choices made on the command line (`get --osc52`, `--selection`)
+/// or by a library caller, on top of `[clipboard]`. Unset ones fall back to
+/// `CLIPM_CLIPBOARD` and `CLIPM_SELECTION`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClipboardOverrides {
+    /// E.g. `Osc52` for `get --osc52`
+    pub backend: Option<Backend>,
+    /// `--selection`
+    #[cfg(all(target_os = "linux", feature = "primary-selection"))]
+    pub selection: Option<Selection>,
+}
+
+/// The two Linux selections `store` and `get` can use.
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Selection {
+    /// The regular clipboard (Ctrl+C / Ctrl+V)
+    Clipboard,
+    /// Whatever text is highlighted, pasted with a middle click
+    Primary,
+}
+
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+impl Selection {
+    /// The selection `SELECTION_ENV` names, `clipboard` or `primary`.
+    fn from_env() -> Option<Selection> {
+        match std::env::var(SELECTION_ENV).ok()?.as_str() {
+            "clipboard" => Some(Selection::Clipboard),
+            "primary" => Some(Selection::Primary),
+            _ => None,
+        }
+    }
+}
+
+/// Where clipboard text is read from and written to.
+pub trait ClipboardProvider {
+    fn get_text(&mut self) -> Result<String, arboard::Error>;
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error>;
+
+    /// RGBA pixels. Only the system clipboard supports images.
+    fn get_image(&mut self) -> Result<ImageData<'static>, arboard::Error> {
+        Err(arboard::Error::ClipboardNotSupported)
+    }
+
+    fn set_image(&mut self, _image: ImageData<'_>) -> Result<(), arboard::Error> {
+        Err(arboard::Error::ClipboardNotSupported)
+    }
+
+    /// The HTML flavor of copied rich text. Only the system clipboard
+    /// supports it.
+    fn get_html(&mut self) -> Result<String, arboard::Error> {
+        Err(arboard::Error::ClipboardNotSupported)
+    }
+
+    /// Set `html` with `text` as its plain-text flavor. Backends without
+    /// rich text just get the text.
+    fn set_html(&mut self, _html: &str, text: &str) -> Result<(), arboard::Error> {
+        self.set_text(text)
+    }
+
+    fn clear(&mut self) -> Result<(), arboard::Error> {
+        self.set_text("")
+    }
+
+    /// Backends that can set the clipboard but never read it back.
+    fn write_only(&self) -> bool {
+        false
+    }
+
+    /// Whether failures may clear up on retry (see `is_transient`). Only the
+    /// system clipboard's can: a missing `copy_cmd` program or an unreadable
+    /// file stays that way.
+    fn transient_failures(&self) -> bool {
+        false
+    }
+}
+
+/// The system clipboard, via arboard.
+struct SystemClipboard(Clipboard);
+
+impl ClipboardProvider for SystemClipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        self.0.get_text()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        self.0.set_text(text)
+    }
+
+    fn get_image(&mut self) -> Result<ImageData<'static>, arboard::Error> {
+        self.0.get_image()
+    }
+
+    fn set_image(&mut self, image: ImageData<'_>) -> Result<(), arboard::Error> {
+        self.0.set_image(image)
+    }
+
+    fn get_html(&mut self) -> Result<String, arboard::Error> {
+        self.0.get().html()
+    }
+
+    fn clear(&mut self) -> Result<(), arboard::Error> {
+        self.0.clear()
+    }
+
+    fn set_html(&mut self, html: &str, text: &str) -> Result<(), arboard::Error> {
+        self.0.set_html(html, Some(text))
+    }
+
+    fn transient_failures(&self) -> bool {
+        true
+    }
+}
+
+/// The Linux primary selection, via arboard (`--selection primary`). Text
+/// only, like the selection itself.
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+struct PrimarySelection(Clipboard);
+
+#[cfg(all(target_os = "linux", feature = "primary-selection"))]
+impl ClipboardProvider for PrimarySelection {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+        self.0.get().clipboard(LinuxClipboardKind::Primary).text()
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        use arboard::{LinuxClipboardKind, SetExtLinux};
+        self.0.set().clipboard(LinuxClipboardKind::Primary).text(text)
+    }
+
+    fn transient_failures(&self) -> bool {
+        true
+    }
+}
+
+/// Process-local clipboard for tests and headless CI (`CLIPM_CLIPBOARD=memory`).
+/// Starts empty; contents don't outlive the process.
+struct MemoryClipboard;
+
+static MEMORY: Mutex<String> = Mutex::new(String::new());
+
+impl ClipboardProvider for MemoryClipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        let text = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+        if text.is_empty() {
+            return Err(arboard::Error::ContentNotAvailable);
+        }
+        Ok(text.clone())
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        *MEMORY.lock().unwrap_or_else(|e| e.into_inner()) = text.to_string();
+        Ok(())
+    }
+}
+
+/// A plain file standing in for the clipboard (`CLIPM_CLIPBOARD=file:/path`),
+/// for containers, SSH sessions without a display, and pipelines.
+struct FileClipboard(PathBuf);
+
+impl ClipboardProvider for FileClipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        match std::fs::read_to_string(&self.0) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(arboard::Error::ContentNotAvailable),
+            Err(e) => Err(file_error(&self.0, e)),
+        }
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        std::fs::write(&self.0, text).map_err(|e| file_error(&self.0, e))
+    }
+}
+
+/// The clipboard of the terminal clipm runs in, set with an OSC 52 escape
+/// sequence (`CLIPM_CLIPBOARD=osc52` or `get --osc52`). Over SSH that is the
+/// local machine's clipboard. Terminals don't let programs read it.
+struct Osc52Clipboard;
+
+/// Written to directly so the sequence reaches the terminal even when
+/// stdout is redirected.
+const TTY: &str = "/dev/tty";
+
+impl ClipboardProvider for Osc52Clipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        Err(arboard::Error::ClipboardNotSupported)
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        let path = std::path::Path::new(TTY);
+        let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|mut tty| tty.write_all(sequence.as_bytes()))
+            .map_err(|e| file_error(path, e))
+    }
+
+    fn write_only(&self) -> bool {
+        true
+    }
+}
+
+/// The OSC 52 "set clipboard" sequence for `text`. Inside tmux it is wrapped
+/// in a passthrough (escapes doubled) so tmux hands it to the outer terminal.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", BASE64.encode(text));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+fn file_error(path: &std::path::Path, e: std::io::Error) -> arboard::Error {
+    arboard::Error::Unknown { description: format!("{}: {e}", path.display()) }
+}
+
+/// External programs such as `wl-copy`/`wl-paste` or `pbcopy`/`pbpaste`,
+/// configured with `copy_cmd`/`paste_cmd`. Text goes to the copy command's
+/// stdin and comes from the paste command's stdout.
+struct CommandClipboard {
+    copy: Option<String>,
+    paste: Option<String>,
+}
+
+impl ClipboardProvider for CommandClipboard {
+    fn get_text(&mut self) -> Result<String, arboard::Error> {
+        let line = self.paste.as_deref().ok_or(arboard::Error::ClipboardNotSupported)?;
+        let output = command(line)?
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| command_error(line, e))?;
+        if !output.status.success() {
+            return Err(command_error(line, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        String::from_utf8(output.stdout).map_err(|_| arboard::Error::ConversionFailure)
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), arboard::Error> {
+        let line = self.copy.as_deref().ok_or(arboard::Error::ClipboardNotSupported)?;
+        // stdout/stderr are not captured: `wl-copy` and `xclip` fork a process
+        // that keeps serving the selection and would hold the pipes open.
+        let mut child = command(line)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| command_error(line, e))?;
+        let written = child.stdin.take().map(|mut stdin| stdin.write_all(text.as_bytes()));
+        let status = child.wait().map_err(|e| command_error(line, e))?;
+        if let Some(Err(e)) = written {
+            return Err(command_error(line, e));
+        }
+        if !status.success() {
+            return Err(command_error(line, status));
+        }
+        Ok(())
+    }
+}
+
+/// Split a configured command line on whitespace. No shell is involved, so
+/// quoting and pipes are not supported.
+fn command(line: &str) -> Result<Command, arboard::Error> {
+    let mut words = line.split_whitespace();
+    let program = words.next().ok_or(arboard::Error::ClipboardNotSupported)?;
+    let mut cmd = Command::new(program);
+    cmd.args(words);
+    Ok(cmd)
+}
+
+fn command_error(line: &str, detail: impl std::fmt::Display) -> arboard::Error {
+    arboard::Error::Unknown { description: format!("`{line}`: {detail}") }
+}
+
+/// Pick the backend: the overridden or `CLIPM_CLIPBOARD` one (`memory`,
+/// `file:/path` or `osc52`) first, then (on Linux) the primary selection if
+/// `--selection primary` asked for it, then `copy_cmd`/`paste_cmd` from
+/// config, otherwise the system clipboard. In an SSH session where the
+/// system clipboard can't be opened (no display), OSC 52 takes its place.
+fn provider(config: &ClipboardConfig) -> Result<Box<dyn ClipboardProvider>, arboard::Error> {
+    match config.overrides.backend.clone().or_else(Backend::from_env) {
+        Some(Backend::Memory) => return Ok(Box::new(MemoryClipboard)),
+        Some(Backend::File(path)) => return Ok(Box::new(FileClipboard(path))),
+        Some(Backend::Osc52) => return Ok(Box::new(Osc52Clipboard)),
+        None => {}
+    }
+    // Ahead of copy_cmd/paste_cmd, which only know one selection
+    #[cfg(all(target_os = "linux", feature = "primary-selection"))]
+    if config.overrides.selection.or_else(Selection::from_env) == Some(Selection::Primary) {
+        return Ok(Box::new(PrimarySelection(Clipboard::new()?)));
+    }
+    if config.copy_cmd.is_some() || config.paste_cmd.is_some() {
+        return Ok(Box::new(CommandClipboard { copy: config.copy_cmd.clone(), paste: config.paste_cmd.clone() }));
+    }
+    match Clipboard::new() {
+        Ok(clipboard) => Ok(Box::new(SystemClipboard(clipboard))),
+        Err(_) if std::env::var_os("SSH_TTY").is_some() => Ok(Box::new(Osc52Clipboard)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the backend can only write (OSC 52), so `store` has to take its
+/// text from piped input instead.
+pub fn write_only(config: &ClipboardConfig) -> bool {
+    provider(config).is_ok_and(|p| p.write_only())
+}
+
+pub fn read_text(config: &ClipboardConfig) -> Result<String, ClipmError> {
+    let text = with_provider(config, |p| p.get_text())?;
     if text.is_empty() {
         return Err(ClipmError::EmptyClipboard);
     }
     Ok(text)
 }
 
-pub fn write_text(text: &str) -> Result<(), ClipmError> {
-    let mut cb = Clipboard::new()?;
-    cb.set_text(text)?;
-    Ok(())
+pub fn write_text(config: &ClipboardConfig, text: &str) -> Result<(), ClipmError> {
+    with_provider(config, |p| p.set_text(text))
+}
+
+pub fn clear(config: &ClipboardConfig) -> Result<(), ClipmError> {
+    with_provider(config, |p| p.clear())
+}
+
+/// The HTML flavor on the clipboard, if the app that copied offered one.
+/// Best effort: `store` keeps the plain text either way.
+pub fn read_html(config: &ClipboardConfig) -> Option<String> {
+    with_provider(config, |p| p.get_html()).ok().filter(|html| !html.is_empty())
+}
+
+/// Put `text` on the clipboard, together with `html` when given so rich
+/// editors paste it formatted.
+pub fn write_rich_text(config: &ClipboardConfig, text: &str, html: Option<&str>) -> Result<(), ClipmError> {
+    match html {
+        Some(html) => with_provider(config, |p| p.set_html(html, text)),
+        None => write_text(config, text),
+    }
+}
+
+/// The image on the clipboard, PNG-encoded. `EmptyClipboard` if there is none.
+pub fn read_image(config: &ClipboardConfig) -> Result<ClipImage, ClipmError> {
+    let image = with_provider(config, |p| p.get_image())?;
+    if image.width == 0 || image.height == 0 {
+        return Err(ClipmError::EmptyClipboard);
+    }
+    encode_png(&image)
+}
+
+pub fn write_image(config: &ClipboardConfig, image: &ClipImage) -> Result<(), ClipmError> {
+    let pixels = decode_png(image)?;
+    with_provider(config, |p| {
+        p.set_image(ImageData {
+            width: image.width as usize,
+            height: image.height as usize,
+            bytes: Cow::Borrowed(&pixels),
+        })
+    })
+}
+
+fn encode_png(image: &ImageData) -> Result<ClipImage, ClipmError> {
+    let png_error = |e: png::EncodingError| ClipmError::Clipboard(e.to_string());
+    let (width, height) = (image.width as u32, image.height as u32);
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&image.bytes).map_err(png_error)?;
+    writer.finish().map_err(png_error)?;
+    Ok(ClipImage { width, height, png })
+}
+
+/// Normalize any PNG to the 8-bit RGBA form `write_image` expects, e.g. for
+/// images piped into `store --stdin`.
+pub fn image_from_png(data: &[u8]) -> Result<ClipImage, ClipmError> {
+    let png_error = |e: png::DecodingError| ClipmError::InvalidInput(t!("Not a PNG image: {error}", error = e));
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(png_error)?;
+    pixels.truncate(info.buffer_size());
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(ClipmError::InvalidInput(t!("Unsupported PNG color type"))),
+    };
+    encode_png(&ImageData { width: info.width as usize, height: info.height as usize, bytes: Cow::Owned(rgba) })
+}
+
+/// RGBA pixels of a PNG written by `encode_png`.
+fn decode_png(image: &ClipImage) -> Result<Vec<u8>, ClipmError> {
+    let png_error = |e: png::DecodingError| ClipmError::Clipboard(e.to_string());
+    let mut decoder = png::Decoder::new(image.png.as_slice());
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(png_error)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(ClipmError::Clipboard(t!("Stored image is not 8-bit RGBA")));
+    }
+    pixels.truncate(info.buffer_size());
+    Ok(pixels)
+}
+
+/// Write `text` (and `html`, see `write_rich_text`), then read the text back
+/// to confirm it stuck. Clipboard sync tools and some apps replace the
+/// contents right after a write, so a mismatch is retried once before
+/// reporting an error.
+pub fn write_text_verified(config: &ClipboardConfig, text: &str, html: Option<&str>) -> Result<(), ClipmError> {
+    // Nothing to read back from; the terminal either took it or didn't
+    if write_only(config) {
+        return write_rich_text(config, text, html);
+    }
+    for _ in 0..VERIFY_ATTEMPTS {
+        write_rich_text(config, text, html)?;
+        std::thread::sleep(VERIFY_DELAY);
+        // The read-back is the secret itself when copying a password
+        let read_back = with_provider(config, |p| p.get_text()).ok().map(SecretString::new);
+        if read_back.as_deref().map(String::as_str) == Some(text) {
+            return Ok(());
+        }
+    }
+    Err(ClipmError::Clipboard(t!("Copy did not stick: the clipboard was changed by another application")))
+}
+
+/// Run `op` on the configured backend, retrying the failures it reports as
+/// transient.
+fn with_provider<T, F>(config: &ClipboardConfig, mut op: F) -> Result<T, ClipmError>
+where
+    F: FnMut(&mut dyn ClipboardProvider) -> Result<T, arboard::Error>,
+{
+    // Opening the system clipboard can fail transiently too
+    let retryable = Cell::new(true);
+    with_retry(
+        config,
+        || {
+            let mut provider = provider(config)?;
+            retryable.set(provider.transient_failures());
+            op(provider.as_mut())
+        },
+        |e| retryable.get() && is_transient(e),
+    )
+}
+
+/// Run a clipboard operation, retrying failures `retryable` accepts up to
+/// `config.retries` times with exponential backoff.
+fn with_retry<T, F, R>(config: &ClipboardConfig, mut op: F, retryable: R) -> Result<T, ClipmError>
+where
+    F: FnMut() -> Result<T, arboard::Error>,
+    R: Fn(&arboard::Error) -> bool,
+{
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < config.retries && retryable(&e) => {
+                std::thread::sleep(backoff(config.retry_delay_ms, attempt));
+                attempt += 1;
+            }
+            result => return result.map_err(ClipmError::from),
+        }
+    }
+}
+
+/// Another process holding the clipboard, or an opaque platform failure
+/// (X11 selection timeouts surface as `Unknown`), may clear up on retry.
+fn is_transient(e: &arboard::Error) -> bool {
+    matches!(e, arboard::Error::ClipboardOccupied | arboard::Error::Unknown { .. })
+}
+
+fn backoff(base_ms: u64, attempt: u32) -> Duration {
+    Duration::from_millis(base_ms.saturating_mul(1 << attempt.min(16)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast(retries: u32) -> ClipboardConfig {
+        ClipboardConfig { retries, retry_delay_ms: 0, ..ClipboardConfig::default() }
+    }
+
+    #[test]
+    fn test_with_retry_recovers_from_transient_errors() {
+        let mut calls = 0;
+        let out = with_retry(&fast(3), || {
+            calls += 1;
+            if calls < 3 { Err(arboard::Error::ClipboardOccupied) } else { Ok(calls) }
+        }, is_transient);
+        assert_eq!(out.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_with_retry_gives_up_after_retries() {
+        let mut calls = 0;
+        let out: Result<(), _> = with_retry(&fast(2), || {
+            calls += 1;
+            Err(arboard::Error::ClipboardOccupied)
+        }, is_transient);
+        assert!(matches!(out, Err(ClipmError::Clipboard(_))));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_with_retry_does_not_retry_permanent_errors() {
+        let mut calls = 0;
+        let out: Result<(), _> = with_retry(&fast(5), || {
+            calls += 1;
+            Err(arboard::Error::ClipboardNotSupported)
+        }, is_transient);
+        assert!(out.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_only_system_clipboard_failures_are_retried() {
+        let mut cb = CommandClipboard { copy: Some("clipm-no-such-program".into()), paste: None };
+        assert!(matches!(cb.set_text("x"), Err(arboard::Error::Unknown { .. })));
+        assert!(!cb.transient_failures());
+        assert!(!FileClipboard(PathBuf::from("/nonexistent/dir/clipboard")).transient_failures());
+        assert!(!Osc52Clipboard.transient_failures());
+    }
+
+    #[test]
+    fn test_file_clipboard_round_trip() {
+        let path = std::env::temp_dir().join(format!("clipm-file-clipboard-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut cb = FileClipboard(path.clone());
+        assert!(matches!(cb.get_text(), Err(arboard::Error::ContentNotAvailable)));
+        cb.set_text("line one\nline two").unwrap();
+        assert_eq!(cb.get_text().unwrap(), "line one\nline two");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_command_clipboard_round_trip() {
+        let path = std::env::temp_dir().join(format!("clipm-command-clipboard-{}", std::process::id()));
+        let mut cb = CommandClipboard {
+            copy: Some(format!("tee {}", path.display())),
+            paste: Some(format!("cat {}", path.display())),
+        };
+        cb.set_text("via commands").unwrap();
+        assert_eq!(cb.get_text().unwrap(), "via commands");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_command_clipboard_failures() {
+        let mut cb = CommandClipboard { copy: None, paste: Some("false".into()) };
+        assert!(matches!(cb.get_text(), Err(arboard::Error::Unknown { .. })));
+        assert!(matches!(cb.set_text("x"), Err(arboard::Error::ClipboardNotSupported)));
+    }
+
+    #[test]
+    fn test_backend_override() {
+        let path = std::env::temp_dir().join(format!("clipm-override-clipboard-{}", std::process::id()));
+        let mut config = ClipboardConfig { retries: 0, ..ClipboardConfig::default() };
+        config.overrides.backend = Some(Backend::File(path.clone()));
+        write_text(&config, "overridden").unwrap();
+        assert_eq!(read_text(&config).unwrap(), "overridden");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_set_html_falls_back_to_text() {
+        let path = std::env::temp_dir().join(format!("clipm-html-clipboard-{}", std::process::id()));
+        let mut cb = FileClipboard(path.clone());
+        cb.set_html("<b>bold</b>", "bold").unwrap();
+        assert_eq!(cb.get_text().unwrap(), "bold");
+        assert!(matches!(cb.get_html(), Err(arboard::Error::ClipboardNotSupported)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let rgba: Vec<u8> = (0..2 * 3 * 4).map(|i| i as u8 * 10).collect();
+        let image = ImageData { width: 2, height: 3, bytes: Cow::Borrowed(&rgba) };
+        let encoded = encode_png(&image).unwrap();
+        assert_eq!((encoded.width, encoded.height), (2, 3));
+        assert!(encoded.png.starts_with(b"\x89PNG"));
+        assert_eq!(decode_png(&encoded).unwrap(), rgba);
+    }
+
+    #[test]
+    fn test_image_from_rgb_png() {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[1, 2, 3, 4, 5, 6]).unwrap();
+        writer.finish().unwrap();
+
+        let image = image_from_png(&png).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(decode_png(&image).unwrap(), vec![1, 2, 3, 255, 4, 5, 6, 255]);
+        assert!(matches!(image_from_png(b"not a png"), Err(ClipmError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        assert_eq!(backoff(100, 0), Duration::from_millis(100));
+        assert_eq!(backoff(100, 1), Duration::from_millis(200));
+        assert_eq!(backoff(100, 3), Duration::from_millis(800));
+    }
 }
@@ -0,0 +1,93 @@
+//! `clipm completions`: clap_complete's scripts, extended so zsh and fish
+//! offer existing labels and recent entry IDs (from the hidden
+//! `clipm __complete` subcommand) as values.
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::cli::Cli;
+
+/// zsh helpers turning `clipm __complete` lines ("value<TAB>description")
+/// into `_describe` items ("value:description", colons in values escaped).
+const ZSH_HELPERS: &str = r#"
+_clipm_values() {
+    local -a items
+    items=(${(f)"$(clipm __complete $1 2>/dev/null)"})
+    items=(${items//:/\\:})
+    items=(${items//$'\t'/:})
+    _describe $2 items
+}
+_clipm_labels() { _clipm_values labels label }
+_clipm_ids() { _clipm_values ids entry }
+"#;
+
+/// fish reads `clipm __complete` output as is: a tab separates value and description.
+const FISH_EXTRAS: &str = r#"
+# Labels and entry IDs from the history
+complete -c clipm -n "__fish_seen_subcommand_from list search export" -s L -l label -x -a "(clipm __complete labels 2>/dev/null)"
+complete -c clipm -n "__fish_seen_subcommand_from store" -s l -l label -x -a "(clipm __complete labels 2>/dev/null)"
+complete -c clipm -n "__fish_seen_subcommand_from label" -l last -x -a "(clipm __complete labels 2>/dev/null)"
+complete -c clipm -n "__fish_seen_subcommand_from get label delete merge pin unpin edit convert" -f -a "(clipm __complete ids 2>/dev/null)"
+"#;
+
+pub fn generate(shell: Shell) -> String {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "clipm", &mut script);
+    let script = String::from_utf8_lossy(&script);
+    match shell {
+        Shell::Zsh => patch_zsh(&script),
+        Shell::Fish => format!("{script}{FISH_EXTRAS}"),
+        _ => script.into_owned(),
+    }
+}
+
+/// Complete `LABEL` values and `id`/`ids` arguments with the helpers instead
+/// of `_default` (files), and define the helpers right after `#compdef`.
+fn patch_zsh(script: &str) -> String {
+    let mut out = String::with_capacity(script.len() + ZSH_HELPERS.len());
+    for (i, line) in script.lines().enumerate() {
+        let helper = if line.contains(":LABEL:") {
+            Some("_clipm_labels")
+        } else if line.contains(":id -- ") || line.contains(":ids -- ") {
+            Some("_clipm_ids")
+        } else {
+            None
+        };
+        match helper.zip(line.strip_suffix(":_default' \\")) {
+            Some((helper, head)) => out.push_str(&format!("{head}:{helper}' \\")),
+            None => out.push_str(line),
+        }
+        out.push('\n');
+        if i == 0 {
+            out.push_str(ZSH_HELPERS);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_zsh() {
+        let script = "#compdef clipm\n\
+                      '-L+[Filter by label]:LABEL:_default' \\\n\
+                      '::id -- Entry ID or @N for the Nth most recent:_default' \\\n\
+                      '*::ids -- Entry IDs to delete:_default' \\\n\
+                      '-f+[Output format]:FORMAT:(table plain json)' \\\n";
+        let patched = patch_zsh(script);
+        assert!(patched.starts_with("#compdef clipm\n\n_clipm_values() {"));
+        assert!(patched.contains("'-L+[Filter by label]:LABEL:_clipm_labels' \\\n"));
+        assert!(patched.contains("'::id -- Entry ID or @N for the Nth most recent:_clipm_ids' \\\n"));
+        assert!(patched.contains("'*::ids -- Entry IDs to delete:_clipm_ids' \\\n"));
+        assert!(patched.contains("'-f+[Output format]:FORMAT:(table plain json)' \\\n"));
+    }
+
+    #[test]
+    fn test_generate_includes_dynamic_values() {
+        assert!(generate(Shell::Zsh).contains("_clipm_ids() {"));
+        assert!(generate(Shell::Fish).contains("clipm __complete labels"));
+        assert!(!generate(Shell::Bash).contains("clipm __complete"));
+    }
+}
@@ -0,0 +1,630 @@
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::clipboard::ClipboardOverrides;
+use crate::models::{ClipmError, ContentType, ListSort, OutputFormat, SortOrder};
+use crate::normalize::Case;
+use crate::timeparse::parse_duration;
+
+/// User configuration, read from `config.toml` next to the history database.
+/// Every field has a default so a missing or partial file is fine.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub confirm: ConfirmPolicy,
+    /// Language for messages (e.g. "bg"); falls back to LC_ALL/LC_MESSAGES/LANG
+    pub locale: Option<String>,
+    pub store: StoreConfig,
+    pub list: ListConfig,
+    pub search: SearchConfig,
+    /// Per-label limits, e.g. `[quotas.scratch] max_entries = 100`
+    pub quotas: HashMap<String, LabelQuota>,
+    pub retention: RetentionConfig,
+    pub clipboard: ClipboardConfig,
+    pub encryption: EncryptionConfig,
+    pub snapshot: SnapshotConfig,
+    pub normalize: NormalizeConfig,
+    pub team: TeamConfig,
+    /// Label and type new text entries by their content, first match wins
+    pub rules: Vec<Rule>,
+    /// Directories whose commands use another profile's history
+    pub workspaces: Vec<Workspace>,
+}
+
+/// Commands run in `dir` or below it use `profile`'s history, e.g. one per
+/// client checkout. The most specific matching directory wins.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    /// A directory such as a git checkout; `~/` is the home directory
+    pub dir: PathBuf,
+    pub profile: String,
+}
+
+/// A `[[rules]]` entry: text that `pattern` (a regex, `match` in the file)
+/// is found in gets `label` and/or `content_type`, unless `store` was given
+/// `--label` or `--type`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(rename = "match", deserialize_with = "deserialize_regex")]
+    pub pattern: regex::Regex,
+    pub label: Option<String>,
+    #[serde(default, rename = "type", deserialize_with = "deserialize_rule_type")]
+    pub content_type: Option<ContentType>,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<regex::Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    regex::Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_rule_type<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ContentType>, D::Error> {
+    match ContentType::deserialize(deserializer)? {
+        ContentType::Image => Err(serde::de::Error::custom("rules can't turn text into an image")),
+        content_type => Ok(Some(content_type)),
+    }
+}
+
+/// Clean-ups `store` applies to text before saving it. Passwords and
+/// images are never changed.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NormalizeConfig {
+    /// Strip spaces and tabs at line ends and blank lines at the end
+    pub trim_trailing_whitespace: bool,
+    /// Turn Windows line endings (CRLF) into LF
+    pub crlf_to_lf: bool,
+    /// Remove zero-width spaces, joiners and byte order marks
+    pub strip_zero_width: bool,
+    /// Remove utm_* and other tracking parameters from a copied URL
+    pub strip_tracking_params: bool,
+    /// Turn smart quotes, dashes, ellipses and non-breaking spaces into ASCII
+    pub ascii_punctuation: bool,
+    /// Compose characters into Unicode NFC (`e` + combining accent -> `é`)
+    pub nfc: bool,
+    /// Respell identifiers; only `get --case` sets it, never the config file
+    #[serde(skip)]
+    pub case: Option<Case>,
+}
+
+/// A shared snippet database, e.g. one a team keeps in a git repository.
+/// `list`, `search` and `get --team` read it next to the personal history;
+/// nothing is ever written to it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TeamConfig {
+    /// A clipm `history.db`, e.g. "~/src/team-snippets/history.db"; `~/` is the home directory
+    pub path: Option<PathBuf>,
+}
+
+/// Passive capture: with `clipm init --launchd`, a launch agent runs
+/// `clipm store` on this schedule.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// Minutes between snapshots; unset or 0 installs no snapshot agent
+    pub interval_minutes: Option<u32>,
+    /// Label for snapshot entries [default: "snapshot"]
+    pub label: Option<String>,
+}
+
+/// Keep the key of an encrypted history in an age-encrypted file instead of
+/// the Keychain.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// age identity file that can decrypt the key file, e.g. "~/.config/age/clipm.txt"
+    pub identity: Option<PathBuf>,
+    /// Recipient the key file is encrypted to; defaults to the identity's own
+    pub recipient: Option<String>,
+    /// Keep the content of password entries in the macOS Keychain, leaving
+    /// only their metadata in the database
+    pub keychain_passwords: bool,
+}
+
+/// Retry policy for clipboard access, which can fail transiently when
+/// another app holds the clipboard.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    /// Extra attempts after a transient failure (0 = fail immediately)
+    pub retries: u32,
+    /// Delay before the first retry; doubles on each further retry
+    pub retry_delay_ms: u64,
+    /// External program that receives copied text on stdin, e.g. "wl-copy"
+    pub copy_cmd: Option<String>,
+    /// External program that prints the clipboard on stdout, e.g. "wl-paste --no-newline"
+    pub paste_cmd: Option<String>,
+    /// Backend and selection from the command line; not a config key
+    #[serde(skip)]
+    pub overrides: ClipboardOverrides,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        ClipboardConfig { retries: 3, retry_delay_ms: 50, copy_cmd: None, paste_cmd: None, overrides: ClipboardOverrides::default() }
+    }
+}
+
+/// History-wide limits applied by `prune`. Unset limits are not enforced.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub max_age_days: Option<u32>,
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+    /// Also apply the limits above after every `store`
+    pub prune_on_store: bool,
+    /// Age after which password entries are purged whenever the history is
+    /// opened, e.g. "24h" or "7d"; "off" keeps them
+    #[serde(deserialize_with = "deserialize_retention")]
+    pub password_retention: Option<chrono::Duration>,
+    /// Days deleted entries stay in the trash before they are purged
+    pub trash_days: u32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            max_age_days: None,
+            max_entries: None,
+            max_bytes: None,
+            prune_on_store: false,
+            password_retention: Some(chrono::Duration::hours(24)),
+            trash_days: 30,
+        }
+    }
+}
+
+fn deserialize_retention<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<chrono::Duration>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    if text == "off" || text == "0" {
+        return Ok(None);
+    }
+    parse_duration(&text).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Caps on the entries stored under one label. When a store pushes the label
+/// over a limit, its oldest entries are deleted.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LabelQuota {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// When destructive commands ask for confirmation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmPolicy {
+    /// Confirm operations that remove more than one entry
+    #[default]
+    Auto,
+    /// Confirm every deletion, including single entries
+    Always,
+    /// Never prompt
+    Never,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    /// Ask for a label after every interactive `store`
+    pub prompt_label: bool,
+    /// Minutes during which re-storing the same content is skipped even if
+    /// other entries were stored in between (0 = only check the latest entry)
+    pub dedup_window: u32,
+    /// Record the working directory, git branch and terminal of each store
+    pub capture_env: bool,
+    /// Milliseconds within which a new store replaces the previous entry
+    /// instead of adding one, so clipboard bursts leave one entry (0 = off)
+    pub coalesce_ms: u32,
+    /// Don't store text that is only whitespace
+    pub skip_whitespace: bool,
+    /// Don't store text shorter than this many characters, ignoring
+    /// surrounding whitespace (0 = off)
+    pub min_chars: usize,
+    /// Skip content that is anywhere in history, like `store --unique`
+    pub unique: bool,
+    /// Content bigger than this many bytes is handled by `oversize`
+    pub max_entry_size: Option<u64>,
+    pub oversize: OversizePolicy,
+}
+
+/// What `store` does with content over `[store] max_entry_size`. Passwords
+/// are always skipped, and images are skipped instead of truncated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizePolicy {
+    /// Don't store it
+    #[default]
+    Skip,
+    /// Store the first `max_entry_size` bytes
+    Truncate,
+    /// Save it to a file in `oversize_dir()` and store the file's path
+    Reference,
+}
+
+/// Defaults for `list` flags; command-line values take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListConfig {
+    pub limit: Option<usize>,
+    pub days: Option<u32>,
+    #[serde(rename = "type")]
+    pub content_type: Option<ContentType>,
+    pub format: Option<OutputFormat>,
+    pub sort: Option<ListSort>,
+    pub order: Option<SortOrder>,
+}
+
+/// Defaults for `search` flags; command-line values take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    pub limit: Option<usize>,
+    pub days: Option<u32>,
+    #[serde(rename = "type")]
+    pub content_type: Option<ContentType>,
+    pub format: Option<OutputFormat>,
+}
+
+/// Commented-out reference config written by `clipm init`.
+pub const DEFAULT_CONFIG: &str = r#"# clipm configuration. Every key is optional; uncomment to change a default.
+
+# confirm = "auto"        # auto | always | never
+# locale = "bg"           # message language; defaults to LC_ALL / LC_MESSAGES / LANG
+
+[store]
+# prompt_label = false    # ask for a label after each interactive store
+# dedup_window = 0        # minutes in which re-storing the same content is skipped
+# capture_env = false     # record directory, git branch and terminal with each entry
+# coalesce_ms = 0         # stores this close together update one entry instead of adding more
+# skip_whitespace = false # don't store whitespace-only text such as a stray newline
+# min_chars = 0           # don't store text shorter than this (e.g. 2 skips single characters)
+# unique = false          # skip content found anywhere in history, not just the latest entry
+# max_entry_size = 10485760  # bytes; bigger content is handled by `oversize`
+# oversize = "skip"       # skip | truncate | reference (save to a file, store its path)
+
+[list]
+# limit = 20
+# format = "table"        # table | plain | json
+# sort = "created"        # created | used | size | label
+# order = "desc"          # asc | desc; defaults to asc for label
+
+[search]
+# limit = 20
+# days = 30
+
+[retention]
+# max_age_days = 90
+# max_entries = 5000
+# max_bytes = 104857600
+# prune_on_store = false      # apply the limits above after every store
+# password_retention = "24h"  # password entries are purged after this; "off" keeps them
+# trash_days = 30             # deleted entries can be restored for this long
+
+[clipboard]
+# retries = 3             # extra attempts when the clipboard is busy
+# retry_delay_ms = 50     # first retry delay; doubles each time
+# copy_cmd = "wl-copy"    # use external programs instead of the system clipboard
+# paste_cmd = "wl-paste --no-newline"
+
+[encryption]
+# identity = "~/.config/age/clipm.txt"  # keep the database key in an age-encrypted file
+# recipient = "age1..."                 # defaults to the identity's public key
+# keychain_passwords = false            # keep password entries in the Keychain, not the database
+
+[normalize]
+# trim_trailing_whitespace = false  # strip spaces at line ends and trailing blank lines
+# crlf_to_lf = false                # convert Windows line endings
+# strip_zero_width = false          # remove zero-width characters
+# strip_tracking_params = false     # drop utm_*, fbclid and similar from copied URLs
+# ascii_punctuation = false         # smart quotes and dashes to ASCII (“x” -> "x", — -> --)
+# nfc = false                       # Unicode NFC normalization
+
+[team]
+# path = "~/src/team-snippets/history.db"  # read-only snippets shown by list, search and get --team
+
+[snapshot]
+# interval_minutes = 15   # with `clipm init --launchd`, store the clipboard this often if it changed
+# label = "snapshot"
+
+# [quotas.scratch]
+# max_entries = 100
+
+# [[rules]]               # label and type new text by content; the first match wins
+# match = '\b[A-Z]+-\d+\b'   # regex, e.g. Jira keys like PROJ-123
+# label = "tickets"
+# type = "code"           # any type but image
+
+# [[workspaces]]          # inside ~/work/clientA, use the "clientA" history
+# dir = "~/work/clientA"
+# profile = "clientA"
+"#;
+
+// Paths are not localized: the locale itself is read from the config file
+// (see i18n::detect).
+
+/// Environment variable overriding `data_dir()`, for tests and isolated setups.
+pub const DATA_DIR_ENV: &str = "CLIPM_DATA_DIR";
+
+/// Environment variable naming the profile to use. It takes precedence over
+/// `[[workspaces]]`, and `--profile` over it.
+pub const PROFILE_ENV: &str = "CLIPM_PROFILE";
+
+/// The profile `load_profile` was given, if any.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use `profile` (the global `--profile` flag) for the rest of the process,
+/// ahead of `$CLIPM_PROFILE` and `[[workspaces]]`. `None` leaves the choice
+/// to those. Only the first profile given counts.
+pub fn load_profile(profile: Option<&str>) {
+    if let Some(profile) = profile.filter(|p| !p.is_empty()) {
+        let _ = PROFILE.set(profile.to_string());
+    }
+}
+
+/// `~/Library/Application Support/clipm` (or `$CLIPM_DATA_DIR`), home of the
+/// database and config.
+pub fn data_dir() -> Result<PathBuf, ClipmError> {
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let dir = dirs::data_dir()
+        .ok_or_else(|| ClipmError::Config("Cannot determine data directory".into()))?;
+    Ok(dir.join("clipm"))
+}
+
+/// `data_dir()`, created if missing. The only place that creates it.
+pub fn ensure_data_dir() -> Result<PathBuf, ClipmError> {
+    let dir = data_dir()?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| ClipmError::Io(format!("Cannot create {}: {e}", dir.display())))?;
+    Ok(dir)
+}
+
+pub fn config_path() -> Result<PathBuf, ClipmError> {
+    Ok(data_dir()?.join("config.toml"))
+}
+
+/// `~/` in configured paths means the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The profile in use: the one given to `load_profile`, else
+/// `$CLIPM_PROFILE`, else that of the workspace the current directory is in,
+/// else `None` for the default history.
+pub fn active_profile() -> Result<Option<String>, ClipmError> {
+    let explicit = PROFILE.get().cloned().or_else(|| std::env::var(PROFILE_ENV).ok().filter(|n| !n.is_empty()));
+    if let Some(name) = explicit {
+        return Ok(Some(name));
+    }
+    let cwd = std::env::current_dir()?;
+    Ok(workspace_profile(&load()?.workspaces, &cwd).map(String::from))
+}
+
+/// The profile of the most specific workspace containing `dir`.
+fn workspace_profile<'a>(workspaces: &'a [Workspace], dir: &Path) -> Option<&'a str> {
+    workspaces
+        .iter()
+        .map(|w| {
+            let root = expand_home(&w.dir);
+            (std::fs::canonicalize(&root).unwrap_or(root), w)
+        })
+        .filter(|(root, _)| dir.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(_, w)| w.profile.as_str())
+}
+
+/// Where the active profile's history lives: `data_dir()` itself for the
+/// default history, `profiles/<name>` below it otherwise. Config stays
+/// shared.
+pub fn history_dir() -> Result<PathBuf, ClipmError> {
+    let dir = data_dir()?;
+    let Some(name) = active_profile()? else {
+        return Ok(dir);
+    };
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')) && !name.starts_with('.');
+    if !valid {
+        return Err(ClipmError::Config(format!("Invalid profile name \"{name}\" (use letters, digits, '-', '_' and '.')")));
+    }
+    Ok(dir.join("profiles").join(name))
+}
+
+/// `history_dir()`, created if missing.
+pub fn ensure_history_dir() -> Result<PathBuf, ClipmError> {
+    let dir = history_dir()?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| ClipmError::Io(format!("Cannot create {}: {e}", dir.display())))?;
+    Ok(dir)
+}
+
+/// Content over `[store] max_entry_size` saved by `oversize = "reference"`.
+pub fn oversize_dir() -> Result<PathBuf, ClipmError> {
+    Ok(history_dir()?.join("large"))
+}
+
+/// The database key sealed to `[encryption] identity`, next to the database.
+pub fn key_file_path() -> Result<PathBuf, ClipmError> {
+    Ok(history_dir()?.join("history.key.age"))
+}
+
+pub fn load() -> Result<Config, ClipmError> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    parse(&text).map_err(|e| ClipmError::Config(format!("{}: {e}", path.display())))
+}
+
+fn parse(text: &str) -> Result<Config, ClipmError> {
+    toml::from_str(text).map_err(|e| ClipmError::Config(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty_is_default() {
+        let config = parse("").unwrap();
+        assert!(!config.store.prompt_label);
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config = parse(
+            "[[rules]]\nmatch = '\\b[A-Z]+-\\d+\\b'\nlabel = \"tickets\"\n\n\
+             [[rules]]\nmatch = '^ghp_'\ntype = \"password\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert!(config.rules[0].pattern.is_match("see PROJ-123"));
+        assert_eq!(config.rules[0].label.as_deref(), Some("tickets"));
+        assert_eq!(config.rules[0].content_type, None);
+        assert_eq!(config.rules[1].content_type, Some(ContentType::Password));
+
+        assert!(parse("[[rules]]\nmatch = '('\nlabel = \"x\"\n").is_err());
+        assert!(parse("[[rules]]\nmatch = 'x'\ntype = \"image\"\n").is_err());
+        assert!(parse("[[rules]]\nlabel = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_store_section() {
+        let config = parse("[store]\nprompt_label = true\ndedup_window = 15\ncapture_env = true\ncoalesce_ms = 500\n").unwrap();
+        assert!(config.store.prompt_label);
+        assert_eq!(config.store.dedup_window, 15);
+        assert!(config.store.capture_env);
+        assert_eq!(config.store.coalesce_ms, 500);
+        assert!(!config.store.skip_whitespace);
+        assert_eq!(config.store.min_chars, 0);
+        assert!(!config.store.unique);
+        assert_eq!(config.store.max_entry_size, None);
+        assert_eq!(config.store.oversize, OversizePolicy::Skip);
+
+        let config = parse("[store]\nmax_entry_size = 1048576\noversize = \"reference\"\n").unwrap();
+        assert_eq!(config.store.max_entry_size, Some(1_048_576));
+        assert_eq!(config.store.oversize, OversizePolicy::Reference);
+    }
+
+    #[test]
+    fn test_parse_confirm_policy() {
+        assert_eq!(parse("").unwrap().confirm, ConfirmPolicy::Auto);
+        assert_eq!(parse("confirm = \"never\"").unwrap().confirm, ConfirmPolicy::Never);
+        assert_eq!(parse("confirm = \"always\"").unwrap().confirm, ConfirmPolicy::Always);
+        assert!(parse("confirm = \"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_command_defaults() {
+        let config = parse(
+            "[list]\nlimit = 50\ntype = \"password\"\nformat = \"plain\"\nsort = \"size\"\n\n[search]\ndays = 30\n",
+        )
+        .unwrap();
+        assert_eq!(config.list.sort, Some(ListSort::Size));
+        assert_eq!(config.list.order, None);
+        assert_eq!(config.list.limit, Some(50));
+        assert_eq!(config.list.content_type, Some(ContentType::Password));
+        assert_eq!(config.list.format, Some(OutputFormat::Plain));
+        assert_eq!(config.list.days, None);
+        assert_eq!(config.search.days, Some(30));
+        assert_eq!(config.search.limit, None);
+    }
+
+    #[test]
+    fn test_parse_quotas() {
+        let config = parse("[quotas.scratch]\nmax_entries = 100\n\n[quotas.logs]\nmax_bytes = 1048576\n").unwrap();
+        assert_eq!(config.quotas["scratch"].max_entries, Some(100));
+        assert_eq!(config.quotas["scratch"].max_bytes, None);
+        assert_eq!(config.quotas["logs"].max_bytes, Some(1_048_576));
+    }
+
+    #[test]
+    fn test_parse_retention() {
+        let config = parse("[retention]\nmax_age_days = 90\nmax_entries = 5000\n").unwrap();
+        assert_eq!(config.retention.max_age_days, Some(90));
+        assert_eq!(config.retention.max_entries, Some(5000));
+        assert_eq!(config.retention.max_bytes, None);
+        assert_eq!(config.retention.password_retention, Some(chrono::Duration::hours(24)));
+        assert_eq!(config.retention.trash_days, 30);
+    }
+
+    #[test]
+    fn test_parse_password_retention() {
+        let config = parse("[retention]\npassword_retention = \"30m\"\n").unwrap();
+        assert_eq!(config.retention.password_retention, Some(chrono::Duration::minutes(30)));
+        let config = parse("[retention]\npassword_retention = \"off\"\n").unwrap();
+        assert_eq!(config.retention.password_retention, None);
+        assert!(parse("[retention]\npassword_retention = \"soon\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_encryption() {
+        assert!(parse("").unwrap().encryption.identity.is_none());
+        let config = parse("[encryption]\nidentity = \"~/keys/clipm.txt\"\n").unwrap();
+        assert_eq!(config.encryption.identity, Some(PathBuf::from("~/keys/clipm.txt")));
+        assert!(config.encryption.recipient.is_none());
+    }
+
+    #[test]
+    fn test_parse_clipboard_retry() {
+        assert_eq!(parse("").unwrap().clipboard.retries, 3);
+        let config = parse("[clipboard]\nretries = 0\nretry_delay_ms = 200\n").unwrap();
+        assert_eq!(config.clipboard.retries, 0);
+        assert_eq!(config.clipboard.retry_delay_ms, 200);
+        assert!(config.clipboard.copy_cmd.is_none());
+    }
+
+    #[test]
+    fn test_parse_clipboard_commands() {
+        let config = parse("[clipboard]\ncopy_cmd = \"wl-copy\"\npaste_cmd = \"wl-paste --no-newline\"\n").unwrap();
+        assert_eq!(config.clipboard.copy_cmd.as_deref(), Some("wl-copy"));
+        assert_eq!(config.clipboard.paste_cmd.as_deref(), Some("wl-paste --no-newline"));
+    }
+
+    #[test]
+    fn test_default_config_parses() {
+        let config = parse(DEFAULT_CONFIG).unwrap();
+        assert!(!config.store.prompt_label);
+        assert_eq!(config.confirm, ConfirmPolicy::Auto);
+        assert_eq!(config.snapshot.interval_minutes, None);
+    }
+
+    #[test]
+    fn test_workspace_profile() {
+        let config = parse(
+            "[[workspaces]]\ndir = \"/work/clientA\"\nprofile = \"clientA\"\n\n\
+             [[workspaces]]\ndir = \"/work/clientA/legacy\"\nprofile = \"legacy\"\n",
+        )
+        .unwrap();
+        let profile = |dir: &str| workspace_profile(&config.workspaces, Path::new(dir));
+        assert_eq!(profile("/work/clientA"), Some("clientA"));
+        assert_eq!(profile("/work/clientA/api/src"), Some("clientA"));
+        assert_eq!(profile("/work/clientA/legacy/app"), Some("legacy"));
+        assert_eq!(profile("/work/clientAB"), None);
+        assert_eq!(profile("/home/me"), None);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(expand_home(Path::new("/etc/key.txt")), PathBuf::from("/etc/key.txt"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_home(Path::new("~/keys/clipm.txt")), home.join("keys/clipm.txt"));
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        let err = parse("[store]\nprompt_lable = true\n").unwrap_err();
+        assert!(matches!(err, ClipmError::Config(_)));
+    }
+}
@@ -1,30 +1,152 @@
-use rusqlite::{Connection, params};
-use std::path::PathBuf;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::{Connection, OpenFlags, Transaction, TransactionBehavior, params};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config;
+use crate::encryption;
+use crate::i18n::t;
+use crate::keychain;
+use crate::models::{make_preview, ClipEntry, ClipImage, ClipSummary, ClipmError, ContentType, EntryRef, ListSort, Provenance, SecretString, Session, Snippet, SortOrder, Source, Usage};
+
+pub fn db_path() -> Result<PathBuf, ClipmError> {
+    Ok(config::ensure_history_dir()?.join("history.db"))
+}
 
-use crate::models::{ClipEntry, ClipmError, ContentType};
+/// Open the history, first purging password entries older than
+/// `[retention] password_retention` and trash older than `trash_days`.
+pub fn open() -> Result<Connection, ClipmError> {
+    let mut conn = open_at(&db_path()?)?;
+    let retention = config::load()?.retention;
+    let now = chrono::Utc::now();
+    if let Some(max_age) = retention.password_retention {
+        expire_passwords(&mut conn, &(now - max_age).to_rfc3339())?;
+    }
+    let trash_cutoff = (now - chrono::Duration::days(i64::from(retention.trash_days))).to_rfc3339();
+    with_write_tx(&mut conn, |tx| purge_trash(tx, &trash_cutoff))?;
+    sweep_keychain(&conn)?;
+    Ok(conn)
+}
 
-fn db_path() -> Result<PathBuf, ClipmError> {
-    let dir = dirs::data_dir()
-        .ok_or_else(|| ClipmError::Database("Cannot determine data directory".into()))?
-        .join("clipm");
-    std::fs::create_dir_all(&dir)
-        .map_err(|e| ClipmError::Database(format!("Cannot create data directory: {e}")))?;
-    Ok(dir.join("history.db"))
+/// Open (creating and migrating if needed) the database at `path`. Encrypted
+/// databases are unlocked with `encryption::passphrase()`.
+pub fn open_at(path: &Path) -> Result<Connection, ClipmError> {
+    let key = if encryption::is_encrypted(path)? { Some(encryption::passphrase()?) } else { None };
+    open_with_key(path, key.as_ref().map(|k| k.as_str()))
 }
 
-pub fn open() -> Result<Connection, ClipmError> {
-    let path = db_path()?;
-    let conn = Connection::open(path)?;
+/// Like `open_at` with an explicit key. A key on a new file creates an
+/// encrypted database.
+pub fn open_with_key(path: &Path, key: Option<&str>) -> Result<Connection, ClipmError> {
+    let mut conn = Connection::open(path)?;
+    if let Some(key) = key {
+        encryption::apply_key(&conn, key)?;
+    }
+    // busy_timeout first: switching a fresh file to WAL needs a lock that a
+    // concurrently starting process may hold
     conn.execute_batch(
-        "PRAGMA journal_mode=WAL;
+        "PRAGMA busy_timeout=5000;
+         PRAGMA journal_mode=WAL;
          PRAGMA foreign_keys=ON;
-         PRAGMA busy_timeout=5000;
          PRAGMA synchronous=NORMAL;"
     )?;
-    migrate(&conn)?;
+    migrate_locked(&mut conn)?;
     Ok(conn)
 }
 
+/// The `[team]` database, if one is configured, opened read-only.
+pub fn open_team() -> Result<Option<Connection>, ClipmError> {
+    match config::load()?.team.path {
+        Some(path) => open_team_at(&config::expand_home(&path)).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Open a team database for reading. It is never migrated, so it must be on
+/// this version's schema, and it can't be encrypted.
+pub fn open_team_at(path: &Path) -> Result<Connection, ClipmError> {
+    if !path.exists() {
+        return Err(ClipmError::Config(t!("The team database {path} does not exist", path = path.display())));
+    }
+    if encryption::is_encrypted(path)? {
+        return Err(ClipmError::Config(t!("The team database {path} is encrypted; only unencrypted team databases are supported", path = path.display())));
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.execute_batch("PRAGMA busy_timeout=5000;")?;
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if version != SCHEMA_VERSION {
+        return Err(ClipmError::Config(t!(
+            "The team database {path} has schema version {found}, but this clipm needs {expected}; open it with the same clipm version",
+            path = path.display(),
+            found = version,
+            expected = SCHEMA_VERSION
+        )));
+    }
+    Ok(conn)
+}
+
+/// Latest `PRAGMA user_version`; bump together with a new `migrate` step.
+const SCHEMA_VERSION: i64 = 17;
+
+/// Run migrations while holding the write lock, so two processes starting at
+/// once can't both apply the same step. The version is re-read under the lock
+/// by `migrate`, so the loser of the race finds nothing left to do.
+fn migrate_locked(conn: &mut Connection) -> Result<(), ClipmError> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    if version >= SCHEMA_VERSION {
+        return Ok(());
+    }
+    with_write_tx(conn, |tx| migrate(tx))
+}
+
+/// Attempts to take the write lock before giving up with the busy error.
+const BUSY_RETRIES: u32 = 5;
+
+fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e,
+        rusqlite::Error::SqliteFailure(err, _)
+            if matches!(err.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Exponential backoff (50ms, 100ms, ...) plus up to the same amount of jitter
+/// so competing writers don't retry in lockstep.
+fn busy_backoff(attempt: u32) -> Duration {
+    let base_ms = 50u64 << attempt;
+    let jitter_ms = std::collections::hash_map::RandomState::new().build_hasher().finish() % base_ms;
+    Duration::from_millis(base_ms + jitter_ms)
+}
+
+/// Run `f` in an IMMEDIATE transaction so the write lock is taken up front
+/// (no mid-transaction "database is locked"), retrying with backoff while
+/// another process holds it. Commits when `f` succeeds, rolls back otherwise.
+pub fn with_write_tx<T, F>(conn: &mut Connection, mut f: F) -> Result<T, ClipmError>
+where
+    F: FnMut(&Transaction) -> Result<T, ClipmError>,
+{
+    let mut attempt = 0;
+    loop {
+        let tx = match conn.transaction_with_behavior(TransactionBehavior::Immediate) {
+            Ok(tx) => tx,
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                std::thread::sleep(busy_backoff(attempt));
+                attempt += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let value = f(&tx)?;
+        tx.commit()?;
+        return Ok(value);
+    }
+}
+
 fn migrate(conn: &Connection) -> Result<(), ClipmError> {
     let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
 
@@ -123,162 +245,1352 @@ fn migrate(conn: &Connection) -> Result<(), ClipmError> {
         )?;
     }
 
+    if version < 3 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS counters (
+                name  TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            );
+
+            PRAGMA user_version = 3;"
+        )?;
+    }
+
+    if version < 4 {
+        // Same rules as models::make_preview: 200 chars, line breaks to spaces,
+        // nothing for passwords
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN preview TEXT NOT NULL DEFAULT '';
+
+            UPDATE clips SET preview = CASE
+                WHEN content_type = 'password' THEN ''
+                ELSE substr(
+                    replace(replace(replace(content, char(13) || char(10), ' '), char(10), ' '), char(13), ' '),
+                    1, 200
+                )
+            END;
+
+            PRAGMA user_version = 4;"
+        )?;
+    }
+
+    if version < 5 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN image BLOB;
+            ALTER TABLE clips ADD COLUMN image_width INTEGER;
+            ALTER TABLE clips ADD COLUMN image_height INTEGER;
+
+            PRAGMA user_version = 5;"
+        )?;
+    }
+
+    if version < 6 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN cwd TEXT;
+            ALTER TABLE clips ADD COLUMN git_branch TEXT;
+            ALTER TABLE clips ADD COLUMN tty TEXT;
+
+            PRAGMA user_version = 6;"
+        )?;
+    }
+
+    if version < 7 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                name        TEXT,
+                started_at  TEXT NOT NULL,
+                ended_at    TEXT
+            );
+
+            ALTER TABLE clips ADD COLUMN session_id INTEGER;
+            CREATE INDEX IF NOT EXISTS idx_clips_session ON clips(session_id);
+
+            PRAGMA user_version = 7;"
+        )?;
+    }
+
+    if version < 8 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+            PRAGMA user_version = 8;"
+        )?;
+    }
+
+    if version < 9 {
+        // Same columns as clips plus when the entry was deleted; entries
+        // deleted by one command share a deleted_at
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS trash (
+                id          INTEGER PRIMARY KEY,
+                content     TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                byte_size   INTEGER NOT NULL,
+                created_at  TEXT NOT NULL,
+                label       TEXT,
+                preview     TEXT NOT NULL DEFAULT '',
+                image       BLOB,
+                image_width INTEGER,
+                image_height INTEGER,
+                cwd         TEXT,
+                git_branch  TEXT,
+                tty         TEXT,
+                session_id  INTEGER,
+                pinned      INTEGER NOT NULL DEFAULT 0,
+                deleted_at  TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_trash_deleted_at ON trash(deleted_at);
+
+            PRAGMA user_version = 9;"
+        )?;
+    }
+
+    if version < 10 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN trigger TEXT;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_clips_trigger ON clips(trigger) WHERE trigger IS NOT NULL;
+            ALTER TABLE trash ADD COLUMN trigger TEXT;
+            PRAGMA user_version = 10;"
+        )?;
+    }
+
+    if version < 11 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS stats (
+                name        TEXT PRIMARY KEY,
+                value       INTEGER NOT NULL
+            );
+
+            PRAGMA user_version = 11;"
+        )?;
+    }
+
+    if version < 12 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE clips ADD COLUMN last_used_at TEXT;
+            ALTER TABLE trash ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE trash ADD COLUMN last_used_at TEXT;
+            PRAGMA user_version = 12;"
+        )?;
+    }
+
+    if version < 13 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN title TEXT;
+            ALTER TABLE trash ADD COLUMN title TEXT;
+            PRAGMA user_version = 13;"
+        )?;
+    }
+
+    if version < 14 {
+        // An entry moves between clips and trash by insert-then-delete, so
+        // its Keychain item is only orphaned once neither table has it
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN keychain_account TEXT;
+            ALTER TABLE trash ADD COLUMN keychain_account TEXT;
+
+            CREATE TABLE IF NOT EXISTS keychain_orphans (
+                account     TEXT PRIMARY KEY
+            );
+
+            CREATE TRIGGER IF NOT EXISTS clips_keychain_orphan AFTER DELETE ON clips
+            WHEN old.keychain_account IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM trash WHERE keychain_account = old.keychain_account)
+            BEGIN
+                INSERT OR IGNORE INTO keychain_orphans (account) VALUES (old.keychain_account);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trash_keychain_orphan AFTER DELETE ON trash
+            WHEN old.keychain_account IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM clips WHERE keychain_account = old.keychain_account)
+            BEGIN
+                INSERT OR IGNORE INTO keychain_orphans (account) VALUES (old.keychain_account);
+            END;
+
+            PRAGMA user_version = 14;"
+        )?;
+    }
+
+    if version < 15 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN content_hash TEXT;
+            CREATE INDEX IF NOT EXISTS idx_clips_content_hash ON clips(content_hash);
+            ALTER TABLE trash ADD COLUMN content_hash TEXT;"
+        )?;
+        backfill_hashes(conn, "clips")?;
+        backfill_hashes(conn, "trash")?;
+        conn.execute_batch("PRAGMA user_version = 15;")?;
+    }
+
+    if version < 16 {
+        conn.execute_batch(
+            "ALTER TABLE clips ADD COLUMN content_html TEXT;
+            ALTER TABLE trash ADD COLUMN content_html TEXT;
+
+            PRAGMA user_version = 16;"
+        )?;
+    }
+
+    if version < 17 {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS snippets (
+                id          INTEGER PRIMARY KEY AUTOINCREMENT,
+                name        TEXT NOT NULL UNIQUE,
+                content     TEXT NOT NULL,
+                label       TEXT,
+                created_at  TEXT NOT NULL
+            );
+
+            PRAGMA user_version = 17;"
+        )?;
+    }
+
     Ok(())
 }
 
-fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<ClipEntry> {
-    let content_type_str: String = row.get(2)?;
-    let content_type = content_type_str.parse::<ContentType>().map_err(|e| {
+/// Set `content_hash` on the rows of `table` (`clips` or `trash`) stored
+/// before the column existed.
+fn backfill_hashes(conn: &Connection, table: &str) -> Result<(), ClipmError> {
+    let mut stmt = conn.prepare(&format!("SELECT id, content, image FROM {table} WHERE content_type <> 'password' AND content_hash IS NULL"))?;
+    let hashes = stmt
+        .query_map([], |r| {
+            let (content, png) = (r.get::<_, String>(1)?, r.get::<_, Option<Vec<u8>>>(2)?);
+            Ok((r.get::<_, i64>(0)?, hash_bytes(png.as_deref().unwrap_or(content.as_bytes()))))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut update = conn.prepare(&format!("UPDATE {table} SET content_hash = ?1 WHERE id = ?2"))?;
+    for (id, hash) in hashes {
+        update.execute(params![hash, id])?;
+    }
+    Ok(())
+}
+
+fn content_type_at(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<ContentType> {
+    let content_type_str: String = row.get(idx)?;
+    content_type_str.parse::<ContentType>().map_err(|e| {
         rusqlite::Error::FromSqlConversionFailure(
-            2,
+            idx,
             rusqlite::types::Type::Text,
             Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
         )
-    })?;
+    })
+}
+
+/// Columns for `row_to_entry`, in order.
+const ENTRY_COLUMNS: &str = "id, content, content_type, byte_size, created_at, label, preview, image, image_width, image_height, cwd, git_branch, tty, session_id, use_count, last_used_at, title, keychain_account, content_html";
+
+/// The `content` column, or for a password kept in the Keychain (account
+/// name at `account_idx`), the secret stored there.
+fn content_at(row: &rusqlite::Row, idx: usize, account_idx: usize) -> rusqlite::Result<String> {
+    let Some(account) = row.get::<_, Option<String>>(account_idx)? else {
+        return row.get(idx);
+    };
+    keychain::get(&account)
+        .and_then(|encoded| BASE64.decode(encoded.as_bytes()).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| {
+            let message = t!("The password is missing from the Keychain (account {account})", account = account);
+            rusqlite::Error::FromSqlConversionFailure(
+                idx,
+                rusqlite::types::Type::Text,
+                Box::new(std::io::Error::new(std::io::ErrorKind::NotFound, message)),
+            )
+        })
+}
+
+fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<ClipEntry> {
+    let image = match row.get::<_, Option<Vec<u8>>>(7)? {
+        Some(png) => Some(ClipImage { png, width: row.get(8)?, height: row.get(9)? }),
+        None => None,
+    };
     Ok(ClipEntry {
         id: row.get(0)?,
-        content: row.get(1)?,
-        content_type,
+        content: content_at(row, 1, 17)?,
+        content_type: content_type_at(row, 2)?,
         byte_size: row.get::<_, i64>(3)? as usize,
         created_at: row.get(4)?,
         label: row.get(5)?,
+        title: row.get(16)?,
+        preview: row.get(6)?,
+        image,
+        html: row.get(18)?,
+        provenance: Provenance { cwd: row.get(10)?, git_branch: row.get(11)?, tty: row.get(12)?, session_id: row.get(13)? },
+        usage: Usage { count: row.get(14)?, last_used_at: row.get(15)? },
     })
 }
 
+/// Columns for `row_to_summary`, in order. Never includes `content`.
+const SUMMARY_COLUMNS: &str = "id, preview, content_type, byte_size, created_at, label, image_width, image_height, use_count, last_used_at, title";
+
+fn row_to_summary(row: &rusqlite::Row) -> rusqlite::Result<ClipSummary> {
+    Ok(ClipSummary {
+        id: row.get(0)?,
+        preview: row.get(1)?,
+        content_type: content_type_at(row, 2)?,
+        byte_size: row.get::<_, i64>(3)? as usize,
+        created_at: row.get(4)?,
+        label: row.get(5)?,
+        title: row.get(10)?,
+        image_size: match (row.get::<_, Option<u32>>(6)?, row.get::<_, Option<u32>>(7)?) {
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => None,
+        },
+        usage: Usage { count: row.get(8)?, last_used_at: row.get(9)? },
+        source: Source::Personal,
+    })
+}
+
+/// Whether the most recent entry has the same text, compared by hash.
 pub fn is_duplicate(conn: &Connection, content: &str) -> Result<bool, ClipmError> {
     let mut stmt = conn.prepare(
-        "SELECT 1 FROM clips WHERE id = (SELECT MAX(id) FROM clips) AND content = ?1"
+        "SELECT 1 FROM clips WHERE id = (SELECT MAX(id) FROM clips) AND content_hash = ?1 AND image IS NULL"
+    )?;
+    Ok(stmt.exists(params![content_hash(content, None)])?)
+}
+
+/// Whether an entry anywhere in history has this `content_hash` (an index
+/// lookup). Passwords have no hash, so they never match.
+pub fn is_stored(conn: &Connection, hash: &str) -> Result<bool, ClipmError> {
+    let mut stmt = conn.prepare("SELECT 1 FROM clips WHERE content_hash = ?1 LIMIT 1")?;
+    Ok(stmt.exists(params![hash])?)
+}
+
+/// Whether the most recent entry is the same image (compared as PNG bytes).
+pub fn is_duplicate_image(conn: &Connection, png: &[u8]) -> Result<bool, ClipmError> {
+    let mut stmt = conn.prepare(
+        "SELECT 1 FROM clips WHERE id = (SELECT MAX(id) FROM clips) AND image = ?1"
+    )?;
+    Ok(stmt.exists(params![png])?)
+}
+
+/// Hex SHA-256 of an entry's content (PNG bytes for images), for spotting the
+/// same clip anywhere in history or across histories.
+pub fn content_hash(content: &str, image: Option<&ClipImage>) -> String {
+    hash_bytes(image.map_or(content.as_bytes(), |i| i.png.as_slice()))
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The `content_hash` column: none for passwords, whose unsalted hash could
+/// be looked up in a dictionary.
+fn stored_hash(content: &str, content_type: &ContentType, image: Option<&ClipImage>) -> Option<String> {
+    (*content_type != ContentType::Password).then(|| content_hash(content, image))
+}
+
+/// `content_hash` of every entry in history. Passwords kept in the Keychain
+/// are hashed on their secret, so an import still recognizes them.
+pub fn content_hashes(conn: &Connection) -> Result<HashSet<String>, ClipmError> {
+    let mut stmt = conn.prepare("SELECT content, image, keychain_account FROM clips")?;
+    let rows = stmt.query_map([], |r| Ok((SecretString::new(content_at(r, 0, 2)?), r.get::<_, Option<Vec<u8>>>(1)?)))?;
+    let mut hashes = HashSet::new();
+    for row in rows {
+        let (content, png) = row?;
+        hashes.insert(hash_bytes(png.as_deref().unwrap_or(content.as_bytes())));
+    }
+    Ok(hashes)
+}
+
+/// Whether `content` was stored at or after `since` (RFC 3339), anywhere in history.
+pub fn is_duplicate_since(conn: &Connection, content: &str, since: &str) -> Result<bool, ClipmError> {
+    let mut stmt = conn.prepare(
+        "SELECT 1 FROM clips WHERE content_hash = ?1 AND image IS NULL AND created_at >= ?2 LIMIT 1"
     )?;
-    Ok(stmt.exists(params![content])?)
+    Ok(stmt.exists(params![content_hash(content, None), since])?)
 }
 
 pub fn insert(conn: &Connection, entry: &ClipEntry) -> Result<i64, ClipmError> {
     conn.execute(
-        "INSERT INTO clips (content, content_type, byte_size, created_at, label)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO clips (content, content_type, byte_size, created_at, label, preview, image, image_width, image_height, cwd, git_branch, tty, session_id, title, content_hash, content_html)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
         params![
             entry.content,
             entry.content_type.to_string(),
             entry.byte_size as i64,
             entry.created_at,
             entry.label,
+            make_preview(&entry.content, &entry.content_type),
+            entry.image.as_ref().map(|i| &i.png),
+            entry.image.as_ref().map(|i| i.width),
+            entry.image.as_ref().map(|i| i.height),
+            entry.provenance.cwd,
+            entry.provenance.git_branch,
+            entry.provenance.tty,
+            entry.provenance.session_id,
+            entry.title,
+            stored_hash(&entry.content, &entry.content_type, entry.image.as_ref()),
+            entry.html,
         ],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+/// Insert a password entry with only its metadata in the database and the
+/// secret itself in the Keychain (base64, as `security` prints anything but
+/// plain ASCII in hex). Run it in a transaction so the entry is rolled back
+/// if the Keychain refuses the secret.
+pub fn insert_secret(conn: &Connection, entry: &ClipEntry) -> Result<i64, ClipmError> {
+    let mut metadata = entry.clone();
+    metadata.content.zeroize();
+    let id = insert(conn, &metadata)?;
+    let account = save_secret(id, &entry.content)?;
+    conn.execute("UPDATE clips SET keychain_account = ?1 WHERE id = ?2", params![account, id])?;
+    Ok(id)
+}
+
+/// Store the secret of entry `id` in the Keychain under a fresh account,
+/// which is returned.
+fn save_secret(id: i64, content: &str) -> Result<String, ClipmError> {
+    let account = format!("entry-{id}-{}", &encryption::random_key()?[..16]);
+    if !keychain::set(&account, &SecretString::new(BASE64.encode(content)))? {
+        return Err(ClipmError::Io(t!("Could not save the password in the Keychain")));
+    }
+    Ok(account)
+}
+
+/// Remove the Keychain items of password entries deleted for good, as
+/// recorded by the `*_keychain_orphan` triggers. Items the Keychain won't
+/// remove stay recorded and are retried next time.
+pub fn sweep_keychain(conn: &Connection) -> Result<(), ClipmError> {
+    let mut stmt = conn.prepare("SELECT account FROM keychain_orphans")?;
+    let accounts = stmt.query_map([], |r| r.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+    for account in accounts {
+        if keychain::delete(&account)? {
+            conn.execute("DELETE FROM keychain_orphans WHERE account = ?1", params![account])?;
+        }
+    }
+    Ok(())
+}
+
+/// The newest entry, if it was stored at or after `since` and a clipboard
+/// burst may overwrite it: no label, title, pin or trigger, and not a password.
+pub fn burst_entry(conn: &Connection, since: &str) -> Result<Option<i64>, ClipmError> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM clips WHERE id = (SELECT MAX(id) FROM clips) AND created_at >= ?1
+         AND label IS NULL AND title IS NULL AND pinned = 0 AND trigger IS NULL AND content_type != ?2"
+    )?;
+    let mut rows = stmt.query_map(params![since, ContentType::Password.to_string()], |r| r.get(0))?;
+    Ok(rows.next().transpose()?)
+}
+
+/// Overwrite an entry's content, type and timestamp with `entry`'s, keeping
+/// its ID, label and provenance.
+pub fn replace_content(conn: &Connection, id: i64, entry: &ClipEntry) -> Result<(), ClipmError> {
+    let changed = conn.execute(
+        "UPDATE clips SET content = ?1, content_type = ?2, byte_size = ?3, created_at = ?4, preview = ?5,
+         image = ?6, image_width = ?7, image_height = ?8, content_hash = ?10, content_html = ?11 WHERE id = ?9",
+        params![
+            entry.content,
+            entry.content_type.to_string(),
+            entry.byte_size as i64,
+            entry.created_at,
+            make_preview(&entry.content, &entry.content_type),
+            entry.image.as_ref().map(|i| &i.png),
+            entry.image.as_ref().map(|i| i.width),
+            entry.image.as_ref().map(|i| i.height),
+            id,
+            stored_hash(&entry.content, &entry.content_type, entry.image.as_ref()),
+            entry.html,
+        ],
+    )?;
+    if changed == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+    }
+    Ok(())
+}
+
 pub fn get_by_id(conn: &Connection, id: i64) -> Result<ClipEntry, ClipmError> {
     conn.query_row(
-        "SELECT id, content, content_type, byte_size, created_at, label FROM clips WHERE id = ?1",
+        &format!("SELECT {ENTRY_COLUMNS} FROM clips WHERE id = ?1"),
         params![id],
         row_to_entry,
     ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(format!("No entry with id {id}")),
+        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No entry with id {id}", id = id)),
         other => ClipmError::Database(other.to_string()),
     })
 }
 
+pub fn get_summary(conn: &Connection, id: i64) -> Result<ClipSummary, ClipmError> {
+    conn.query_row(
+        &format!("SELECT {SUMMARY_COLUMNS} FROM clips WHERE id = ?1"),
+        params![id],
+        row_to_summary,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No entry with id {id}", id = id)),
+        other => ClipmError::Database(other.to_string()),
+    })
+}
+
+/// Every label in use, with how many entries carry it, most used first.
+pub fn labels(conn: &Connection) -> Result<Vec<(String, usize)>, ClipmError> {
+    // Snippets share the label namespace, so `tree` and completion show theirs
+    let mut stmt = conn.prepare(
+        "SELECT label, COUNT(*) FROM (SELECT label FROM clips UNION ALL SELECT label FROM snippets)
+         WHERE label IS NOT NULL GROUP BY label ORDER BY COUNT(*) DESC, label",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)? as usize)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+const SNIPPET_COLUMNS: &str = "id, name, content, label, created_at";
+
+fn row_to_snippet(row: &rusqlite::Row) -> rusqlite::Result<Snippet> {
+    Ok(Snippet { id: row.get(0)?, name: row.get(1)?, content: row.get(2)?, label: row.get(3)?, created_at: row.get(4)? })
+}
+
+pub fn add_snippet(conn: &Connection, name: &str, content: &str, label: Option<&str>) -> Result<i64, ClipmError> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM snippets WHERE name = ?1)", params![name], |r| r.get(0))?;
+    if exists {
+        return Err(ClipmError::InvalidInput(t!("A snippet named \"{name}\" already exists", name = name)));
+    }
+    conn.execute(
+        "INSERT INTO snippets (name, content, label, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![name, content, label, chrono::Utc::now().to_rfc3339()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_snippet(conn: &Connection, name: &str) -> Result<Snippet, ClipmError> {
+    conn.query_row(&format!("SELECT {SNIPPET_COLUMNS} FROM snippets WHERE name = ?1"), params![name], row_to_snippet)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No snippet named \"{name}\"", name = name)),
+            e => e.into(),
+        })
+}
+
+/// Snippets by name, limited to `label` and the labels below it
+/// (`work` covers `work/projA`) like `list -L`.
+pub fn snippets(conn: &Connection, label: Option<&str>) -> Result<Vec<Snippet>, ClipmError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SNIPPET_COLUMNS} FROM snippets
+         WHERE ?1 IS NULL OR label = ?1 OR substr(label, 1, length(?1) + 1) = ?1 || '/'
+         ORDER BY name"
+    ))?;
+    let rows = stmt.query_map(params![label], row_to_snippet)?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub fn delete_snippet(conn: &Connection, name: &str) -> Result<(), ClipmError> {
+    if conn.execute("DELETE FROM snippets WHERE name = ?1", params![name])? == 0 {
+        return Err(ClipmError::NotFound(t!("No snippet named \"{name}\"", name = name)));
+    }
+    Ok(())
+}
+
+const SESSION_COLUMNS: &str = "id, name, started_at, ended_at";
+
+fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    Ok(Session { id: row.get(0)?, name: row.get(1)?, started_at: row.get(2)?, ended_at: row.get(3)? })
+}
+
+/// The session new entries are assigned to, if one is running.
+pub fn current_session(conn: &Connection) -> Result<Option<Session>, ClipmError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SESSION_COLUMNS} FROM sessions WHERE ended_at IS NULL ORDER BY id DESC LIMIT 1"
+    ))?;
+    let mut rows = stmt.query_map([], row_to_session)?;
+    Ok(rows.next().transpose()?)
+}
+
+/// End the running session (if any) and start a new one. Returns its ID.
+pub fn start_session(conn: &Connection, name: Option<&str>) -> Result<i64, ClipmError> {
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute("UPDATE sessions SET ended_at = ?1 WHERE ended_at IS NULL", params![now])?;
+    conn.execute("INSERT INTO sessions (name, started_at) VALUES (?1, ?2)", params![name, now])?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// End the running session. Returns it, or `None` if none was running.
+pub fn end_session(conn: &Connection) -> Result<Option<Session>, ClipmError> {
+    let Some(mut session) = current_session(conn)? else {
+        return Ok(None);
+    };
+    let now = chrono::Utc::now().to_rfc3339();
+    conn.execute("UPDATE sessions SET ended_at = ?1 WHERE id = ?2", params![now, session.id])?;
+    session.ended_at = Some(now);
+    Ok(Some(session))
+}
+
+/// The most recent session with this name.
+pub fn find_session(conn: &Connection, name: &str) -> Result<Session, ClipmError> {
+    conn.query_row(
+        &format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE name = ?1 ORDER BY id DESC LIMIT 1"),
+        params![name],
+        row_to_session,
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No session named \"{name}\"", name = name)),
+        other => other.into(),
+    })
+}
+
+/// The latest sessions, newest first, with how many entries each holds.
+pub fn sessions(conn: &Connection, limit: usize) -> Result<Vec<(Session, usize)>, ClipmError> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.name, s.started_at, s.ended_at, (SELECT COUNT(*) FROM clips c WHERE c.session_id = s.id)
+         FROM sessions s ORDER BY s.id DESC LIMIT ?1",
+    )?;
+    let rows = stmt.query_map(params![limit as i64], |r| Ok((row_to_session(r)?, r.get::<_, i64>(4)? as usize)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
 pub fn update_label(conn: &Connection, id: i64, label: Option<&str>) -> Result<(), ClipmError> {
     let changed = conn.execute(
         "UPDATE clips SET label = ?1 WHERE id = ?2",
         params![label, id],
     )?;
     if changed == 0 {
-        return Err(ClipmError::NotFound(format!("No entry with id {id}")));
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+    }
+    Ok(())
+}
+
+/// Set or (with `None`) remove an entry's title.
+pub fn set_title(conn: &Connection, id: i64, title: Option<&str>) -> Result<(), ClipmError> {
+    let changed = conn.execute("UPDATE clips SET title = ?1 WHERE id = ?2", params![title, id])?;
+    if changed == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+    }
+    Ok(())
+}
+
+/// Count a copy of the entry by `get`.
+pub fn record_use(conn: &Connection, id: i64) -> Result<(), ClipmError> {
+    conn.execute(
+        "UPDATE clips SET use_count = use_count + 1, last_used_at = ?1 WHERE id = ?2",
+        params![chrono::Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// Set or (with `None`) remove an entry's text-expansion trigger. A trigger
+/// names one entry: reusing another entry's is an `InvalidInput` error.
+pub fn set_trigger(conn: &Connection, id: i64, trigger: Option<&str>) -> Result<(), ClipmError> {
+    if let Some(trigger) = trigger {
+        let mut stmt = conn.prepare("SELECT id FROM clips WHERE trigger = ?1 AND id != ?2")?;
+        let owner = stmt.query_map(params![trigger, id], |r| r.get::<_, i64>(0))?.next().transpose()?;
+        if let Some(owner) = owner {
+            return Err(ClipmError::InvalidInput(t!("Trigger \"{trigger}\" is already used by entry #{id}", trigger = trigger, id = owner)));
+        }
+    }
+    let changed = conn.execute("UPDATE clips SET trigger = ?1 WHERE id = ?2", params![trigger, id])?;
+    if changed == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
     }
     Ok(())
 }
 
+pub fn get_by_trigger(conn: &Connection, trigger: &str) -> Result<ClipEntry, ClipmError> {
+    conn.query_row(
+        &format!("SELECT {ENTRY_COLUMNS} FROM clips WHERE trigger = ?1"),
+        params![trigger],
+        row_to_entry,
+    ).map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No entry with trigger \"{trigger}\"", trigger = trigger)),
+        other => ClipmError::Database(other.to_string()),
+    })
+}
+
+/// Every trigger with its entry, sorted by trigger.
+pub fn triggers(conn: &Connection) -> Result<Vec<(String, ClipSummary)>, ClipmError> {
+    let mut stmt = conn.prepare(&format!("SELECT {SUMMARY_COLUMNS}, trigger FROM clips WHERE trigger IS NOT NULL ORDER BY trigger"))?;
+    let rows = stmt.query_map([], |r| Ok((r.get(11)?, row_to_summary(r)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Change a text entry into a password entry or back. The cached preview is
+/// rebuilt, and the FTS triggers mask or unmask the content. With `keychain`
+/// a new password moves into the Keychain like `insert_secret`; a password
+/// leaving the Keychain gets its secret back in `content` and its item is
+/// queued for `sweep_keychain`. Run it in a transaction.
+pub fn set_content_type(conn: &Connection, id: i64, content_type: &ContentType, keychain: bool) -> Result<(), ClipmError> {
+    let entry = get_by_id(conn, id)?;
+    if entry.content_type == ContentType::Image || *content_type == ContentType::Image {
+        return Err(ClipmError::InvalidInput(t!("Only text and password entries can be converted")));
+    }
+    let old_account: Option<String> = conn.query_row("SELECT keychain_account FROM clips WHERE id = ?1", params![id], |r| r.get(0))?;
+    let account = match old_account {
+        Some(account) if *content_type == ContentType::Password => Some(account),
+        Some(account) => {
+            conn.execute("INSERT OR IGNORE INTO keychain_orphans (account) VALUES (?1)", params![account])?;
+            None
+        }
+        None if keychain && *content_type == ContentType::Password => Some(save_secret(id, &entry.content)?),
+        None => None,
+    };
+    let content = if account.is_some() { "" } else { entry.content.as_str() };
+    conn.execute(
+        // A password must not stay readable in its HTML flavor
+        "UPDATE clips SET content_type = ?1, preview = ?2, content_hash = ?3, content = ?4, keychain_account = ?5,
+         content_html = CASE WHEN ?1 = 'password' THEN NULL ELSE content_html END WHERE id = ?6",
+        params![content_type.to_string(), make_preview(&entry.content, content_type), stored_hash(&entry.content, content_type, None), content, account, id],
+    )?;
+    Ok(())
+}
+
+/// Replace a text entry's content in place (`clipm edit`), keeping its type,
+/// timestamps and label. The HTML flavor no longer matches and is dropped.
+/// The FTS triggers pick up the change.
+pub fn set_content(conn: &Connection, id: i64, content: &str) -> Result<(), ClipmError> {
+    let content_type = get_summary(conn, id)?.content_type;
+    if !content_type.is_text() {
+        return Err(ClipmError::InvalidInput(t!("Only text entries can be edited")));
+    }
+    conn.execute(
+        "UPDATE clips SET content = ?1, byte_size = ?2, preview = ?3, content_hash = ?4, content_html = NULL WHERE id = ?5",
+        params![content, content.len() as i64, make_preview(content, &content_type), stored_hash(content, &content_type, None), id],
+    )?;
+    Ok(())
+}
+
+/// Text-like entries whose type differs from what `ContentType::detect`
+/// gives them now, oldest first, with their new type.
+pub fn detected_types(conn: &Connection) -> Result<Vec<(i64, ContentType)>, ClipmError> {
+    let mut stmt = conn.prepare("SELECT id, content_type, content FROM clips WHERE content_type IN ('text', 'url', 'json', 'code') ORDER BY id")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, content_type_at(r, 1)?, r.get::<_, String>(2)?)))?;
+    let mut changes = Vec::new();
+    for row in rows {
+        let (id, current, content) = row?;
+        let detected = ContentType::detect(&content);
+        if detected != current {
+            changes.push((id, detected));
+        }
+    }
+    Ok(changes)
+}
+
+/// Turn `@N` into the ID of the Nth most recent entry. Plain IDs are returned
+/// as-is; whether they exist is up to the caller's query.
+pub fn resolve(conn: &Connection, entry: EntryRef) -> Result<i64, ClipmError> {
+    match entry {
+        EntryRef::Id(id) => Ok(id),
+        EntryRef::Recent(n) => conn
+            .query_row("SELECT id FROM clips ORDER BY id DESC LIMIT 1 OFFSET ?1", params![(n - 1) as i64], |row| row.get(0))
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No entry at @{n}", n = n)),
+                other => other.into(),
+            }),
+    }
+}
+
 pub fn get_most_recent(conn: &Connection) -> Result<ClipEntry, ClipmError> {
     conn.query_row(
-        "SELECT id, content, content_type, byte_size, created_at, label FROM clips ORDER BY id DESC LIMIT 1",
+        &format!("SELECT {ENTRY_COLUMNS} FROM clips ORDER BY id DESC LIMIT 1"),
         [],
         row_to_entry,
     ).map_err(|e| match e {
-        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound("No entries in history".into()),
+        rusqlite::Error::QueryReturnedNoRows => ClipmError::NotFound(t!("No entries in history")),
         other => ClipmError::Database(other.to_string()),
     })
 }
 
-pub fn list(conn: &Connection, limit: usize, offset: usize, label: Option<&str>, days: Option<u32>, content_type: Option<&str>) -> Result<Vec<ClipEntry>, ClipmError> {
-    let mut sql = "SELECT id, content, content_type, byte_size, created_at, label FROM clips WHERE 1=1".to_string();
-    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+/// Every entry of the given type, content included, newest first.
+pub fn entries_of_type(conn: &Connection, content_type: &ContentType) -> Result<Vec<ClipEntry>, ClipmError> {
+    let mut stmt = conn.prepare(&format!("SELECT {ENTRY_COLUMNS} FROM clips WHERE content_type = ?1 ORDER BY id DESC"))?;
+    let entries = stmt.query_map(params![content_type.to_string()], row_to_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Entries created in `[from, to)` (RFC 3339 UTC bounds), oldest first.
+pub fn entries_between(conn: &Connection, from: &str, to: &str) -> Result<Vec<ClipSummary>, ClipmError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM clips WHERE created_at >= ?1 AND created_at < ?2 ORDER BY created_at, id"
+    ))?;
+    let entries = stmt.query_map(params![from, to], row_to_summary)?.collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
 
-    if let Some(l) = label {
+pub fn count(conn: &Connection) -> Result<usize, ClipmError> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clips", [], |r| r.get(0))?;
+    Ok(count as usize)
+}
+
+/// How many entries were created at or after `cutoff` (RFC 3339 UTC).
+pub fn count_since(conn: &Connection, cutoff: &str) -> Result<usize, ClipmError> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clips WHERE created_at >= ?1", params![cutoff], |r| r.get(0))?;
+    Ok(count as usize)
+}
+
+/// Creation time of the oldest unpinned entry (of `content_type`, if given):
+/// the next one retention rules will remove.
+pub fn oldest_unpinned(conn: &Connection, content_type: Option<&ContentType>) -> Result<Option<String>, ClipmError> {
+    let oldest = conn.query_row(
+        "SELECT MIN(created_at) FROM clips WHERE pinned = 0 AND (?1 IS NULL OR content_type = ?1)",
+        params![content_type.map(|ct| ct.to_string())],
+        |r| r.get(0),
+    )?;
+    Ok(oldest)
+}
+
+/// Which entries `export` includes. Unset fields don't filter.
+#[derive(Debug, Default)]
+pub struct ExportFilter {
+    /// Full-text query, as for `search`
+    pub query: Option<String>,
+    pub label: Option<String>,
+    pub days: Option<u32>,
+    pub content_type: Option<ContentType>,
+    pub exclude_passwords: bool,
+}
+
+/// Full entries matching `filter`, oldest first.
+pub fn export(conn: &Connection, filter: &ExportFilter) -> Result<Vec<ClipEntry>, ClipmError> {
+    let mut sql = format!("SELECT {ENTRY_COLUMNS} FROM clips WHERE 1=1");
+    let mut params: SqlParams = Vec::new();
+    if let Some(q) = &filter.query {
+        sql.push_str(" AND id IN (SELECT rowid FROM clips_fts WHERE clips_fts MATCH ?)");
+        params.push(Box::new(fts_query(q)?));
+    }
+    if let Some(l) = &filter.label {
         sql.push_str(" AND label = ?");
-        params.push(Box::new(l.to_string()));
+        params.push(Box::new(l.clone()));
+    }
+    if let Some(d) = filter.days {
+        sql.push_str(" AND created_at >= ?");
+        params.push(Box::new((chrono::Utc::now() - chrono::Duration::days(d as i64)).to_rfc3339()));
+    }
+    if let Some(ct) = &filter.content_type {
+        sql.push_str(" AND content_type = ?");
+        params.push(Box::new(ct.to_string()));
+    }
+    if filter.exclude_passwords {
+        sql.push_str(" AND content_type != ?");
+        params.push(Box::new(ContentType::Password.to_string()));
+    }
+    sql.push_str(" ORDER BY id ASC");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let entries = stmt.query_map(param_refs.as_slice(), row_to_entry)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Which entries `list` shows. Unset fields don't filter.
+#[derive(Debug, Default)]
+pub struct ListFilter {
+    pub label: Option<String>,
+    pub days: Option<u32>,
+    pub content_type: Option<ContentType>,
+    /// Stored in this directory or below it (recorded with `[store] capture_env`)
+    pub cwd: Option<String>,
+    /// Stored on this git branch
+    pub branch: Option<String>,
+    /// Stored during this session
+    pub session: Option<i64>,
+    /// Only pinned entries
+    pub pinned: bool,
+    /// Stored at or after this time
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Stored before this time
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Defaults to `ListSort::Created`
+    pub sort: Option<ListSort>,
+    /// Defaults to the sort's natural direction (`ListSort::default_order`)
+    pub order: Option<SortOrder>,
+}
+
+pub fn list(conn: &Connection, limit: usize, offset: usize, filter: &ListFilter) -> Result<Vec<ClipSummary>, ClipmError> {
+    let mut sql = format!("SELECT {SUMMARY_COLUMNS} FROM clips WHERE 1=1");
+    let mut params: SqlParams = Vec::new();
+
+    if let Some(l) = &filter.label {
+        sql.push_str(" AND label = ?");
+        params.push(Box::new(l.clone()));
     }
 
-    if let Some(d) = days {
+    if let Some(d) = filter.days {
         let cutoff = chrono::Utc::now() - chrono::Duration::days(d as i64);
         let cutoff_str = cutoff.to_rfc3339();
         sql.push_str(" AND created_at >= ?");
         params.push(Box::new(cutoff_str));
     }
 
-    if let Some(ct) = content_type {
+    if let Some(ct) = &filter.content_type {
         sql.push_str(" AND content_type = ?");
         params.push(Box::new(ct.to_string()));
     }
 
-    sql.push_str(" ORDER BY id DESC LIMIT ? OFFSET ?");
+    push_time_range(&mut sql, &mut params, "created_at", filter.since, filter.until);
+
+    if let Some(dir) = &filter.cwd {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        sql.push_str(" AND (cwd = ? OR substr(cwd, 1, ?) = ?)");
+        params.push(Box::new(dir.clone()));
+        params.push(Box::new(prefix.chars().count() as i64));
+        params.push(Box::new(prefix));
+    }
+
+    if let Some(b) = &filter.branch {
+        sql.push_str(" AND git_branch = ?");
+        params.push(Box::new(b.clone()));
+    }
+
+    if let Some(session) = filter.session {
+        sql.push_str(" AND session_id = ?");
+        params.push(Box::new(session));
+    }
+
+    if filter.pinned {
+        sql.push_str(" AND pinned = 1");
+    }
+
+    sql.push_str(&order_by(filter.sort.unwrap_or_default(), filter.order));
+    sql.push_str(" LIMIT ? OFFSET ?");
     params.push(Box::new(limit as i64));
     params.push(Box::new(offset as i64));
 
     let mut stmt = conn.prepare(&sql)?;
     let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-    let entries = stmt.query_map(param_refs.as_slice(), row_to_entry)?
+    let entries = stmt.query_map(param_refs.as_slice(), row_to_summary)?
         .collect::<Result<Vec<_>, _>>()?;
     Ok(entries)
 }
 
-pub fn search(conn: &Connection, query: &str, limit: usize, days: Option<u32>, content_type: Option<&str>) -> Result<Vec<ClipEntry>, ClipmError> {
+/// ORDER BY clause for `list`. Only fixed column names reach the SQL; ties
+/// go to the newest entry.
+fn order_by(sort: ListSort, order: Option<SortOrder>) -> String {
+    let dir = match order.unwrap_or(sort.default_order()) {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    match sort {
+        ListSort::Created => format!(" ORDER BY id {dir}"),
+        ListSort::Used => format!(" ORDER BY use_count {dir}, last_used_at {dir}, id DESC"),
+        ListSort::Size => format!(" ORDER BY byte_size {dir}, id DESC"),
+        ListSort::Label => format!(" ORDER BY label IS NULL, label COLLATE NOCASE {dir}, id DESC"),
+    }
+}
+
+type SqlParams = Vec<Box<dyn rusqlite::types::ToSql>>;
+
+/// `query` as an FTS5 match expression.
+fn fts_query(query: &str) -> Result<String, ClipmError> {
     let trimmed = query.trim();
     if trimmed.is_empty() {
-        return Err(ClipmError::InvalidInput("Empty search query".into()));
+        return Err(ClipmError::InvalidInput(t!("Empty search query")));
     }
-    let escaped = trimmed.replace('"', "\"\"");
+    Ok(trimmed.replace('"', "\"\""))
+}
 
-    let mut sql = "SELECT c.id, c.content, c.content_type, c.byte_size, c.created_at, c.label
-         FROM clips_fts f
+/// `--since`/`--until` bounds on a timestamp column.
+fn push_time_range(sql: &mut String, params: &mut SqlParams, column: &str, since: Option<chrono::DateTime<chrono::Utc>>, until: Option<chrono::DateTime<chrono::Utc>>) {
+    if let Some(since) = since {
+        sql.push_str(&format!(" AND {column} >= ?"));
+        params.push(Box::new(since.to_rfc3339()));
+    }
+    if let Some(until) = until {
+        sql.push_str(&format!(" AND {column} < ?"));
+        params.push(Box::new(until.to_rfc3339()));
+    }
+}
+
+/// FROM/WHERE clause shared by `search` and `search_count`.
+fn search_clause(query: &str, filter: &SearchFilter) -> Result<(String, SqlParams), ClipmError> {
+    let escaped = fts_query(query)?;
+
+    let mut sql = " FROM clips_fts f
          JOIN clips c ON c.id = f.rowid
          WHERE clips_fts MATCH ?1".to_string();
-    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(escaped)];
+    let mut params: SqlParams = vec![Box::new(escaped)];
+    push_search_filters(&mut sql, &mut params, filter);
+    Ok((sql, params))
+}
 
-    if let Some(d) = days {
+/// Append the `search` filters on the `clips` table aliased as `c`.
+fn push_search_filters(sql: &mut String, params: &mut SqlParams, filter: &SearchFilter) {
+    if let Some(d) = filter.days {
         let cutoff = chrono::Utc::now() - chrono::Duration::days(d as i64);
         let cutoff_str = cutoff.to_rfc3339();
         sql.push_str(" AND c.created_at >= ?");
         params.push(Box::new(cutoff_str));
     }
 
-    if let Some(ct) = content_type {
-        sql.push_str(" AND c.content_type = ?");
-        params.push(Box::new(ct.to_string()));
+    if let Some(ct) = &filter.content_type {
+        sql.push_str(" AND c.content_type = ?");
+        params.push(Box::new(ct.to_string()));
+    }
+
+    push_time_range(sql, params, "c.created_at", filter.since, filter.until);
+
+    // The label and everything below it in the hierarchy (`work` covers
+    // `work/projA`). Compared with substr rather than LIKE, which would
+    // ignore case and treat `_`/`%` as wildcards.
+    if let Some(l) = &filter.label {
+        let prefix = format!("{l}/");
+        sql.push_str(" AND (c.label = ? OR substr(c.label, 1, ?) = ?)");
+        params.push(Box::new(l.to_string()));
+        params.push(Box::new(prefix.chars().count() as i64));
+        params.push(Box::new(prefix));
+    }
+}
+
+/// Filters for every search mode: FTS, and those that scan entries in Rust
+/// (`search --fuzzy`, `--regex`).
+#[derive(Debug, Default)]
+pub struct SearchFilter {
+    /// This label or any label below it
+    pub label: Option<String>,
+    pub days: Option<u32>,
+    pub content_type: Option<ContentType>,
+    /// Stored at or after this time
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Stored before this time
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Every entry passing `filter`, newest first, with its content. As in FTS,
+/// password content is withheld (`None`), and so is that of images.
+pub fn scan(conn: &Connection, filter: &SearchFilter) -> Result<Vec<(ClipSummary, Option<String>)>, ClipmError> {
+    let mut sql = "SELECT c.id, c.preview, c.content_type, c.byte_size, c.created_at, c.label, c.image_width, c.image_height, c.use_count, c.last_used_at, c.title,
+                CASE WHEN c.content_type IN ('password', 'image') THEN NULL ELSE c.content END
+         FROM clips c WHERE 1=1".to_string();
+    let mut params: SqlParams = Vec::new();
+    push_search_filters(&mut sql, &mut params, filter);
+    sql.push_str(" ORDER BY c.id DESC");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let rows = stmt.query_map(param_refs.as_slice(), |r| Ok((row_to_summary(r)?, r.get(11)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub fn search(conn: &Connection, query: &str, limit: usize, offset: usize, filter: &SearchFilter) -> Result<Vec<ClipSummary>, ClipmError> {
+    let (clause, mut params) = search_clause(query, filter)?;
+    let sql = format!(
+        "SELECT c.id, c.preview, c.content_type, c.byte_size, c.created_at, c.label, c.image_width, c.image_height, c.use_count, c.last_used_at, c.title{clause}
+         ORDER BY bm25(clips_fts) LIMIT ? OFFSET ?"
+    );
+    params.push(Box::new(limit as i64));
+    params.push(Box::new(offset as i64));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let entries = stmt.query_map(param_refs.as_slice(), row_to_summary)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Total number of matches for a search, ignoring limit/offset.
+pub fn search_count(conn: &Connection, query: &str, filter: &SearchFilter) -> Result<usize, ClipmError> {
+    let (clause, params) = search_clause(query, filter)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let count: i64 = conn.query_row(&format!("SELECT COUNT(*){clause}"), param_refs.as_slice(), |r| r.get(0))?;
+    Ok(count as usize)
+}
+
+/// Entries `reindex` adds to the index per statement, between progress calls.
+const REINDEX_BATCH: i64 = 1000;
+
+/// Rebuild `clips_fts` from `clips`, or with `tokenizer` (an FTS5 `tokenize`
+/// option such as "porter unicode61" or "trigram") recreate it first.
+/// Password content is left out as the sync triggers do, which FTS5's own
+/// 'rebuild' would not. `progress` gets (indexed, total) after each batch.
+/// Run it inside `with_write_tx`.
+pub fn reindex(conn: &Connection, tokenizer: Option<&str>, mut progress: impl FnMut(usize, usize)) -> Result<usize, ClipmError> {
+    let total = count(conn)?;
+    match tokenizer {
+        Some(tokenizer) => {
+            // Goes into the DDL, so only words FTS5 tokenizer options are made of
+            let valid = !tokenizer.trim().is_empty() && tokenizer.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '-'));
+            if !valid {
+                return Err(ClipmError::InvalidInput(t!("Invalid tokenizer: {value}", value = tokenizer)));
+            }
+            conn.execute_batch(&format!(
+                "DROP TABLE clips_fts;
+                 CREATE VIRTUAL TABLE clips_fts USING fts5(content, label, content='clips', content_rowid='id', tokenize = '{tokenizer}');"
+            ))?;
+        }
+        None => {
+            conn.execute("INSERT INTO clips_fts(clips_fts) VALUES('delete-all')", [])?;
+        }
+    }
+    let mut indexed = 0;
+    let mut last_id = 0i64;
+    loop {
+        let added = conn.execute(
+            "INSERT INTO clips_fts(rowid, content, label)
+             SELECT id, CASE WHEN content_type = 'password' THEN '' ELSE content END, label
+             FROM clips WHERE id > ?1 ORDER BY id LIMIT ?2",
+            params![last_id, REINDEX_BATCH],
+        )?;
+        if added == 0 {
+            break;
+        }
+        last_id = conn.query_row(
+            "SELECT MAX(id) FROM (SELECT id FROM clips WHERE id > ?1 ORDER BY id LIMIT ?2)",
+            params![last_id, REINDEX_BATCH],
+            |r| r.get(0),
+        )?;
+        indexed += added;
+        progress(indexed, total);
+    }
+    conn.execute("INSERT INTO clips_fts(clips_fts) VALUES('optimize')", [])?;
+    Ok(indexed)
+}
+
+pub fn delete(conn: &Connection, id: i64) -> Result<(), ClipmError> {
+    let changed = conn.execute("DELETE FROM clips WHERE id = ?1", params![id])?;
+    if changed == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+    }
+    Ok(())
+}
+
+/// Pin or unpin an entry. Pinned entries survive `clear`, pruning, label
+/// quotas and password expiry.
+pub fn set_pinned(conn: &Connection, id: i64, pinned: bool) -> Result<(), ClipmError> {
+    let changed = conn.execute("UPDATE clips SET pinned = ?1 WHERE id = ?2", params![pinned, id])?;
+    if changed == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+    }
+    Ok(())
+}
+
+pub fn pinned_count(conn: &Connection) -> Result<usize, ClipmError> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clips WHERE pinned = 1", [], |r| r.get(0))?;
+    Ok(count as usize)
+}
+
+/// Delete several entries atomically: if any id is missing, nothing is deleted.
+pub fn delete_many(conn: &mut Connection, ids: &[i64]) -> Result<(), ClipmError> {
+    with_write_tx(conn, |tx| {
+        for &id in ids {
+            delete(tx, id)?;
+        }
+        Ok(())
+    })
+}
+
+/// Trim the unpinned entries under `label` to the given limits by deleting
+/// the oldest ones. The newest entry is always kept. Returns how many were
+/// deleted.
+pub fn enforce_quota(conn: &Connection, label: &str, max_entries: Option<usize>, max_bytes: Option<u64>) -> Result<usize, ClipmError> {
+    let mut stmt = conn.prepare("SELECT id, byte_size FROM clips WHERE label = ?1 AND pinned = 0 ORDER BY id DESC")?;
+    let rows = stmt.query_map(params![label], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)? as u64)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut total_bytes = 0u64;
+    let mut evict = Vec::new();
+    for (i, (id, size)) in rows.into_iter().enumerate() {
+        total_bytes += size;
+        let over_count = max_entries.is_some_and(|max| i >= max);
+        let over_bytes = max_bytes.is_some_and(|max| total_bytes > max);
+        if i > 0 && (over_count || over_bytes) {
+            evict.push(id);
+        }
+    }
+
+    for id in &evict {
+        conn.execute("DELETE FROM clips WHERE id = ?1", params![id])?;
+    }
+    Ok(evict.len())
+}
+
+/// IDs of entries that fall outside the retention limits, oldest first:
+/// created before `max_age_days`, beyond the newest `max_entries`, or past
+/// `max_bytes` of content counted from the newest entry. Pinned entries are
+/// never candidates and don't count towards the limits.
+pub fn prune_candidates(conn: &Connection, max_age_days: Option<u32>, max_entries: Option<usize>, max_bytes: Option<u64>) -> Result<Vec<i64>, ClipmError> {
+    let cutoff = max_age_days.map(|d| (chrono::Utc::now() - chrono::Duration::days(d as i64)).to_rfc3339());
+    let mut stmt = conn.prepare("SELECT id, byte_size, created_at FROM clips WHERE pinned = 0 ORDER BY id DESC")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)? as u64, r.get::<_, String>(2)?)))?;
+
+    let mut total_bytes = 0u64;
+    let mut ids = Vec::new();
+    for (i, row) in rows.enumerate() {
+        let (id, size, created_at) = row?;
+        total_bytes += size;
+        let too_old = cutoff.as_ref().is_some_and(|c| created_at.as_str() < c.as_str());
+        let over_count = max_entries.is_some_and(|max| i >= max);
+        let over_bytes = max_bytes.is_some_and(|max| total_bytes > max);
+        if too_old || over_count || over_bytes {
+            ids.push(id);
+        }
+    }
+    ids.reverse();
+    Ok(ids)
+}
+
+/// Columns moved between `clips` and `trash`.
+const TRASH_COLUMNS: &str = "id, content, content_type, byte_size, created_at, label, preview, image, image_width, image_height, cwd, git_branch, tty, session_id, pinned, trigger, use_count, last_used_at, title, keychain_account, content_hash, content_html";
+
+fn move_to_trash(conn: &Connection, condition: &str, deleted_at: &str) -> Result<usize, ClipmError> {
+    let moved = conn.execute(
+        &format!("INSERT INTO trash ({TRASH_COLUMNS}, deleted_at) SELECT {TRASH_COLUMNS}, ?1 FROM clips WHERE {condition}"),
+        params![deleted_at],
+    )?;
+    conn.execute(&format!("DELETE FROM clips WHERE {condition}"), [])?;
+    Ok(moved)
+}
+
+/// Move several entries to the trash atomically, as one step for
+/// `restore_last`. If any id is missing, nothing is moved.
+pub fn trash_many(conn: &mut Connection, ids: &[i64]) -> Result<(), ClipmError> {
+    with_write_tx(conn, |tx| trash_ids(tx, ids))
+}
+
+/// Move `ids` to the trash as one deletion (for `undo`), inside the caller's
+/// transaction.
+pub fn trash_ids(conn: &Connection, ids: &[i64]) -> Result<(), ClipmError> {
+    let deleted_at = chrono::Utc::now().to_rfc3339();
+    for &id in ids {
+        if move_to_trash(conn, &format!("id = {id}"), &deleted_at)? == 0 {
+            return Err(ClipmError::NotFound(t!("No entry with id {id}", id = id)));
+        }
+    }
+    Ok(())
+}
+
+/// Move every unpinned entry to the trash. Returns how many were moved.
+pub fn trash_all(conn: &Connection) -> Result<usize, ClipmError> {
+    move_to_trash(conn, "pinned = 0", &chrono::Utc::now().to_rfc3339())
+}
+
+/// Entries in the trash with when they were deleted, most recently deleted first.
+pub fn trashed(conn: &Connection) -> Result<Vec<(ClipSummary, String)>, ClipmError> {
+    let mut stmt = conn.prepare(&format!("SELECT {SUMMARY_COLUMNS}, deleted_at FROM trash ORDER BY deleted_at DESC, id DESC"))?;
+    let rows = stmt.query_map([], |r| Ok((row_to_summary(r)?, r.get(11)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Move a trashed entry back into history under its old ID. If its trigger
+/// has been given to another entry since, it comes back without one.
+pub fn restore(conn: &Connection, id: i64) -> Result<(), ClipmError> {
+    conn.execute("UPDATE trash SET trigger = NULL WHERE id = ?1 AND trigger IN (SELECT trigger FROM clips)", params![id])?;
+    let restored = conn.execute(
+        &format!("INSERT INTO clips ({TRASH_COLUMNS}) SELECT {TRASH_COLUMNS} FROM trash WHERE id = ?1"),
+        params![id],
+    )?;
+    if restored == 0 {
+        return Err(ClipmError::NotFound(t!("No entry with id {id} in the trash", id = id)));
+    }
+    conn.execute("DELETE FROM trash WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Restore the entries moved to the trash by the most recent delete or
+/// clear. Returns their IDs, empty when the trash is.
+pub fn restore_last(conn: &Connection) -> Result<Vec<i64>, ClipmError> {
+    let mut stmt = conn.prepare("SELECT id FROM trash WHERE deleted_at = (SELECT MAX(deleted_at) FROM trash) ORDER BY id")?;
+    let ids = stmt.query_map([], |r| r.get(0))?.collect::<Result<Vec<i64>, _>>()?;
+    for &id in &ids {
+        restore(conn, id)?;
     }
+    Ok(ids)
+}
 
-    sql.push_str(" ORDER BY bm25(clips_fts) LIMIT ?");
-    params.push(Box::new(limit as i64));
+/// Permanently delete everything in the trash. Returns how many entries were deleted.
+pub fn empty_trash(conn: &Connection) -> Result<usize, ClipmError> {
+    Ok(conn.execute("DELETE FROM trash", [])?)
+}
 
-    let mut stmt = conn.prepare(&sql)?;
-    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-    let entries = stmt.query_map(param_refs.as_slice(), row_to_entry)?
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(entries)
+/// Permanently delete trashed entries deleted before `cutoff`.
+pub fn purge_trash(conn: &Connection, cutoff: &str) -> Result<usize, ClipmError> {
+    Ok(conn.execute("DELETE FROM trash WHERE deleted_at < ?1", params![cutoff])?)
 }
 
-pub fn delete(conn: &Connection, id: i64) -> Result<(), ClipmError> {
-    let changed = conn.execute("DELETE FROM clips WHERE id = ?1", params![id])?;
-    if changed == 0 {
-        return Err(ClipmError::NotFound(format!("No entry with id {id}")));
+/// Delete unpinned password entries created before `cutoff`, in history and
+/// in the trash, with `secure_delete` on, then checkpoint so the old pages
+/// don't linger in the WAL either. Returns how many were deleted from history.
+pub fn expire_passwords(conn: &mut Connection, cutoff: &str) -> Result<usize, ClipmError> {
+    conn.pragma_update(None, "secure_delete", true)?;
+    let deleted = with_write_tx(conn, |tx| {
+        let password = ContentType::Password.to_string();
+        tx.execute("DELETE FROM trash WHERE content_type = ?1 AND created_at < ?2 AND pinned = 0", params![password, cutoff])?;
+        Ok(tx.execute("DELETE FROM clips WHERE content_type = ?1 AND created_at < ?2 AND pinned = 0", params![password, cutoff])?)
+    })?;
+    if deleted > 0 {
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
     }
+    Ok(deleted)
+}
+
+/// Increment the named counter (starting at 1) and return its new value.
+pub fn next_counter(conn: &Connection, name: &str) -> Result<i64, ClipmError> {
+    let value = conn.query_row(
+        "INSERT INTO counters (name, value) VALUES (?1, 1)
+         ON CONFLICT(name) DO UPDATE SET value = value + 1
+         RETURNING value",
+        params![name],
+        |r| r.get(0),
+    )?;
+    Ok(value)
+}
+
+/// Add one to a statistic such as "coalesced" (stores merged into a burst).
+pub fn bump_stat(conn: &Connection, name: &str) -> Result<(), ClipmError> {
+    conn.execute(
+        "INSERT INTO stats (name, value) VALUES (?1, 1) ON CONFLICT(name) DO UPDATE SET value = value + 1",
+        params![name],
+    )?;
     Ok(())
 }
 
-pub fn clear(conn: &Connection) -> Result<usize, ClipmError> {
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clips", [], |r| r.get(0))?;
-    conn.execute_batch("DELETE FROM clips;")?;
-    Ok(count as usize)
+/// A statistic's value, 0 if it was never bumped.
+pub fn stat(conn: &Connection, name: &str) -> Result<i64, ClipmError> {
+    let mut stmt = conn.prepare("SELECT value FROM stats WHERE name = ?1")?;
+    let mut rows = stmt.query_map(params![name], |r| r.get(0))?;
+    Ok(rows.next().transpose()?.unwrap_or(0))
+}
+
+/// Entries with the same type and content as a newer entry (images compare
+/// their PNG bytes), as (newest, older copies newest first) groups. Passwords
+/// kept in the Keychain are left out: the database doesn't hold their content.
+pub fn duplicates(conn: &Connection) -> Result<Vec<(i64, Vec<i64>)>, ClipmError> {
+    let mut stmt = conn.prepare(
+        "SELECT k.keep, c.id FROM clips c
+         JOIN (SELECT MAX(id) AS keep, content_type, content, image FROM clips
+               WHERE keychain_account IS NULL
+               GROUP BY content_type, content, image HAVING COUNT(*) > 1) k
+           ON c.content_type = k.content_type AND c.content = k.content AND c.image IS k.image
+         WHERE c.keychain_account IS NULL AND c.id <> k.keep
+         ORDER BY k.keep, c.id DESC",
+    )?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, i64>(1)?)))?;
+    let mut groups: Vec<(i64, Vec<i64>)> = Vec::new();
+    for row in rows {
+        let (keep, id) = row?;
+        match groups.last_mut() {
+            Some((last, ids)) if *last == keep => ids.push(id),
+            _ => groups.push((keep, vec![id])),
+        }
+    }
+    Ok(groups)
+}
+
+/// Fold `copies` (newest first) into `keep` and delete them for good. Where
+/// `keep` has no label, title or trigger it takes the first copy's that has
+/// one; a pin carries over and uses add up. Returns the bytes freed.
+pub fn merge_duplicates(conn: &Connection, keep: i64, copies: &[i64]) -> Result<u64, ClipmError> {
+    let mut freed = 0u64;
+    for &id in copies {
+        conn.execute(
+            "UPDATE clips SET
+                 label = COALESCE(clips.label, d.label),
+                 title = COALESCE(clips.title, d.title),
+                 pinned = MAX(clips.pinned, d.pinned),
+                 use_count = clips.use_count + d.use_count,
+                 last_used_at = CASE WHEN clips.last_used_at IS NULL OR d.last_used_at > clips.last_used_at
+                                     THEN d.last_used_at ELSE clips.last_used_at END
+             FROM (SELECT label, title, pinned, use_count, last_used_at FROM clips WHERE id = ?2) AS d
+             WHERE clips.id = ?1",
+            params![keep, id],
+        )?;
+        let (trigger, size): (Option<String>, i64) =
+            conn.query_row("SELECT trigger, byte_size FROM clips WHERE id = ?1", params![id], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        delete(conn, id)?;
+        // After the delete: triggers are unique
+        if trigger.is_some() {
+            conn.execute("UPDATE clips SET trigger = COALESCE(trigger, ?2) WHERE id = ?1", params![keep, trigger])?;
+        }
+        freed += size as u64;
+    }
+    Ok(freed)
 }
 
 #[cfg(test)]
@@ -299,6 +1611,12 @@ mod tests {
             byte_size: content.len(),
             created_at: "2026-01-01T00:00:00Z".to_string(),
             label: None,
+            title: None,
+            preview: String::new(),
+            image: None,
+            html: None,
+            provenance: Provenance::default(),
+            usage: Usage::default(),
         }
     }
 
@@ -310,6 +1628,12 @@ mod tests {
             byte_size: content.len(),
             created_at: created_at.to_string(),
             label: None,
+            title: None,
+            preview: String::new(),
+            image: None,
+            html: None,
+            provenance: Provenance::default(),
+            usage: Usage::default(),
         }
     }
 
@@ -332,6 +1656,28 @@ mod tests {
         assert_eq!(recent.content, "second");
     }
 
+    #[test]
+    fn test_resolve_entry_ref() {
+        let conn = test_conn();
+        let first = insert(&conn, &sample_entry("first")).unwrap();
+        let second = insert(&conn, &sample_entry("second")).unwrap();
+        assert_eq!(resolve(&conn, EntryRef::Recent(1)).unwrap(), second);
+        assert_eq!(resolve(&conn, EntryRef::Recent(2)).unwrap(), first);
+        assert_eq!(resolve(&conn, EntryRef::Id(99)).unwrap(), 99);
+        assert!(matches!(resolve(&conn, EntryRef::Recent(3)), Err(ClipmError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_labels_most_used_first() {
+        let conn = test_conn();
+        for (content, label) in [("a", Some("work")), ("b", Some("deploy")), ("c", Some("deploy")), ("d", None)] {
+            let mut entry = sample_entry(content);
+            entry.label = label.map(String::from);
+            insert(&conn, &entry).unwrap();
+        }
+        assert_eq!(labels(&conn).unwrap(), vec![("deploy".to_string(), 2), ("work".to_string(), 1)]);
+    }
+
     #[test]
     fn test_get_most_recent_empty() {
         let conn = test_conn();
@@ -372,15 +1718,202 @@ mod tests {
         assert!(is_duplicate(&conn, "world").unwrap());
     }
 
+    #[test]
+    fn test_is_stored_checks_whole_history() {
+        let conn = test_conn();
+        let id = insert(&conn, &sample_entry("hello")).unwrap();
+        insert(&conn, &sample_entry("world")).unwrap();
+        assert!(is_stored(&conn, &content_hash("hello", None)).unwrap());
+        assert!(!is_stored(&conn, &content_hash("other", None)).unwrap());
+
+        // Passwords keep no hash, also after a conversion
+        set_content_type(&conn, id, &ContentType::Password, false).unwrap();
+        assert!(!is_stored(&conn, &content_hash("hello", None)).unwrap());
+        let mut secret = sample_entry("hunter2");
+        secret.content_type = ContentType::Password;
+        insert(&conn, &secret).unwrap();
+        assert!(!is_stored(&conn, &content_hash("hunter2", None)).unwrap());
+    }
+
+    #[test]
+    fn test_burst_entry_and_replace_content() {
+        let conn = test_conn();
+        let now = chrono::Utc::now();
+        let second_ago = (now - chrono::Duration::seconds(1)).to_rfc3339();
+        let id = insert(&conn, &sample_entry_at("A1", &now.to_rfc3339())).unwrap();
+        assert_eq!(burst_entry(&conn, &second_ago).unwrap(), Some(id));
+        assert_eq!(burst_entry(&conn, &(now + chrono::Duration::seconds(1)).to_rfc3339()).unwrap(), None);
+
+        replace_content(&conn, id, &sample_entry("A1:B4")).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().content, "A1:B4");
+        assert_eq!(search(&conn, "B4", 10, 0, &SearchFilter::default()).unwrap().len(), 1);
+
+        // Labeled entries are never overwritten
+        update_label(&conn, id, Some("keep")).unwrap();
+        assert_eq!(burst_entry(&conn, &second_ago).unwrap(), None);
+    }
+
+    #[test]
+    fn test_set_content() {
+        let conn = test_conn();
+        let id = insert(&conn, &sample_entry("ssh deploy@staging")).unwrap();
+        set_content(&conn, id, "ssh deploy@production").unwrap();
+
+        let entry = get_by_id(&conn, id).unwrap();
+        assert_eq!(entry.content, "ssh deploy@production");
+        assert_eq!(entry.byte_size, "ssh deploy@production".len());
+        assert_eq!(entry.created_at, "2026-01-01T00:00:00Z");
+        assert_eq!(search(&conn, "production", 10, 0, &SearchFilter::default()).unwrap().len(), 1);
+        assert!(search(&conn, "staging", 10, 0, &SearchFilter::default()).unwrap().is_empty());
+        assert!(is_stored(&conn, &content_hash("ssh deploy@production", None)).unwrap());
+
+        set_content_type(&conn, id, &ContentType::Password, false).unwrap();
+        assert!(matches!(set_content(&conn, id, "x"), Err(ClipmError::InvalidInput(_))));
+        assert!(matches!(set_content(&conn, 999, "x"), Err(ClipmError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_html_flavor() {
+        let conn = test_conn();
+        let mut entry = sample_entry("bold move");
+        entry.html = Some("<b>bold</b> move".to_string());
+        let id = insert(&conn, &entry).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().html.as_deref(), Some("<b>bold</b> move"));
+        // Kept through the trash
+        trash_ids(&conn, &[id]).unwrap();
+        restore(&conn, id).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().html.as_deref(), Some("<b>bold</b> move"));
+
+        // Edited text and passwords lose it
+        set_content(&conn, id, "plain move").unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().html, None);
+        let secret = insert(&conn, &entry).unwrap();
+        set_content_type(&conn, secret, &ContentType::Code, false).unwrap();
+        assert!(get_by_id(&conn, secret).unwrap().html.is_some());
+        set_content_type(&conn, secret, &ContentType::Password, false).unwrap();
+        assert_eq!(get_by_id(&conn, secret).unwrap().html, None);
+    }
+
+    #[test]
+    fn test_snippets() {
+        let conn = test_conn();
+        add_snippet(&conn, "greet", "Hi {{name}}", Some("mail")).unwrap();
+        add_snippet(&conn, "sig", "-- me", Some("mail/work")).unwrap();
+        add_snippet(&conn, "todo", "TODO({{date}})", None).unwrap();
+        assert!(matches!(add_snippet(&conn, "greet", "Hello", None), Err(ClipmError::InvalidInput(_))));
+        assert_eq!(get_snippet(&conn, "greet").unwrap().content, "Hi {{name}}");
+        let names = |label| snippets(&conn, label).unwrap().into_iter().map(|s| s.name).collect::<Vec<_>>();
+        assert_eq!(names(None), ["greet", "sig", "todo"]);
+        assert_eq!(names(Some("mail")), ["greet", "sig"]);
+        assert_eq!(names(Some("mai")), Vec::<String>::new());
+
+        // Labels are shared with entries
+        let mut entry = sample_entry("x");
+        entry.label = Some("mail".to_string());
+        insert(&conn, &entry).unwrap();
+        assert_eq!(labels(&conn).unwrap(), [("mail".to_string(), 2), ("mail/work".to_string(), 1)]);
+
+        delete_snippet(&conn, "sig").unwrap();
+        assert!(matches!(get_snippet(&conn, "sig"), Err(ClipmError::NotFound(_))));
+        assert!(matches!(delete_snippet(&conn, "sig"), Err(ClipmError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_record_use_and_sort_by_use() {
+        let conn = test_conn();
+        let a = insert(&conn, &sample_entry("a")).unwrap();
+        let b = insert(&conn, &sample_entry("b")).unwrap();
+        let c = insert(&conn, &sample_entry("c")).unwrap();
+        record_use(&conn, a).unwrap();
+        record_use(&conn, a).unwrap();
+        record_use(&conn, b).unwrap();
+
+        let entry = get_by_id(&conn, a).unwrap();
+        assert_eq!(entry.usage.count, 2);
+        assert!(entry.usage.last_used_at.is_some());
+        let by_use = ListFilter { sort: Some(ListSort::Used), ..Default::default() };
+        let ids: Vec<i64> = list(&conn, 10, 0, &by_use).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![a, b, c]);
+        assert_eq!(get_summary(&conn, c).unwrap().usage, Usage::default());
+    }
+
+    #[test]
+    fn test_since_until() {
+        let conn = test_conn();
+        let now = chrono::Utc::now();
+        let at = |hours_ago| (now - chrono::Duration::hours(hours_ago)).to_rfc3339();
+        let old = insert(&conn, &sample_entry_at("deploy old", &at(48))).unwrap();
+        let mid = insert(&conn, &sample_entry_at("deploy mid", &at(5))).unwrap();
+        let new = insert(&conn, &sample_entry_at("deploy new", &at(1))).unwrap();
+        let (since, until) = (Some(now - chrono::Duration::hours(24)), Some(now - chrono::Duration::hours(2)));
+
+        let ids = |filter: &ListFilter| -> Vec<i64> { list(&conn, 10, 0, filter).unwrap().iter().map(|e| e.id).collect() };
+        assert_eq!(ids(&ListFilter { since, ..Default::default() }), vec![new, mid]);
+        assert_eq!(ids(&ListFilter { until, ..Default::default() }), vec![mid, old]);
+        assert_eq!(ids(&ListFilter { since, until, ..Default::default() }), vec![mid]);
+
+        let filter = SearchFilter { since, until, ..Default::default() };
+        assert_eq!(search_count(&conn, "deploy", &filter).unwrap(), 1);
+        assert_eq!(scan(&conn, &filter).unwrap()[0].0.id, mid);
+    }
+
+    #[test]
+    fn test_list_sort_and_order() {
+        let conn = test_conn();
+        let mut small = sample_entry("a");
+        small.label = Some("beta".to_string());
+        let small = insert(&conn, &small).unwrap();
+        let mut big = sample_entry("a much longer entry");
+        big.byte_size = big.content.len();
+        big.label = Some("Alpha".to_string());
+        let big = insert(&conn, &big).unwrap();
+        let unlabeled = insert(&conn, &sample_entry("mid size")).unwrap();
+
+        let ids = |sort, order| -> Vec<i64> {
+            let filter = ListFilter { sort: Some(sort), order, ..Default::default() };
+            list(&conn, 10, 0, &filter).unwrap().iter().map(|e| e.id).collect()
+        };
+        assert_eq!(ids(ListSort::Created, None), vec![unlabeled, big, small]);
+        assert_eq!(ids(ListSort::Created, Some(SortOrder::Asc)), vec![small, big, unlabeled]);
+        assert_eq!(ids(ListSort::Size, None)[0], big);
+        assert_eq!(ids(ListSort::Label, None), vec![big, small, unlabeled]);
+        assert_eq!(ids(ListSort::Label, Some(SortOrder::Desc)), vec![small, big, unlabeled]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let conn = test_conn();
+        assert_eq!(stat(&conn, "coalesced").unwrap(), 0);
+        bump_stat(&conn, "coalesced").unwrap();
+        bump_stat(&conn, "coalesced").unwrap();
+        assert_eq!(stat(&conn, "coalesced").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_is_duplicate_since() {
+        let conn = test_conn();
+        let now = chrono::Utc::now();
+        let five_min_ago = (now - chrono::Duration::minutes(5)).to_rfc3339();
+        let hour_ago = (now - chrono::Duration::hours(1)).to_rfc3339();
+        insert(&conn, &sample_entry_at("recent", &five_min_ago)).unwrap();
+        insert(&conn, &sample_entry_at("old", &hour_ago)).unwrap();
+        insert(&conn, &sample_entry_at("latest", &now.to_rfc3339())).unwrap();
+
+        let window = (now - chrono::Duration::minutes(10)).to_rfc3339();
+        assert!(is_duplicate_since(&conn, "recent", &window).unwrap());
+        assert!(!is_duplicate_since(&conn, "old", &window).unwrap());
+        assert!(!is_duplicate_since(&conn, "never stored", &window).unwrap());
+    }
+
     #[test]
     fn test_list() {
         let conn = test_conn();
         for i in 0..5 {
             insert(&conn, &sample_entry(&format!("entry {i}"))).unwrap();
         }
-        let entries = list(&conn, 3, 0, None, None, None).unwrap();
+        let entries = list(&conn, 3, 0, &ListFilter::default()).unwrap();
         assert_eq!(entries.len(), 3);
-        assert_eq!(entries[0].content, "entry 4");
+        assert_eq!(entries[0].preview, "entry 4");
     }
 
     #[test]
@@ -389,9 +1922,9 @@ mod tests {
         for i in 0..5 {
             insert(&conn, &sample_entry(&format!("entry {i}"))).unwrap();
         }
-        let entries = list(&conn, 2, 2, None, None, None).unwrap();
+        let entries = list(&conn, 2, 2, &ListFilter::default()).unwrap();
         assert_eq!(entries.len(), 2);
-        assert_eq!(entries[0].content, "entry 2");
+        assert_eq!(entries[0].preview, "entry 2");
     }
 
     #[test]
@@ -402,9 +1935,9 @@ mod tests {
         insert(&conn, &labeled).unwrap();
         insert(&conn, &sample_entry("unlabeled")).unwrap();
 
-        let entries = list(&conn, 10, 0, Some("important"), None, None).unwrap();
+        let entries = list(&conn, 10, 0, &ListFilter { label: Some("important".into()), ..Default::default() }).unwrap();
         assert_eq!(entries.len(), 1);
-        assert_eq!(entries[0].content, "labeled");
+        assert_eq!(entries[0].preview, "labeled");
     }
 
     #[test]
@@ -420,6 +1953,23 @@ mod tests {
         assert_eq!(entry.label, None);
     }
 
+    #[test]
+    fn test_set_title() {
+        let mut conn = test_conn();
+        let id = insert(&conn, &sample_entry("postgres://app@db.internal/prod")).unwrap();
+        set_title(&conn, id, Some("prod DB connection string")).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().title.as_deref(), Some("prod DB connection string"));
+        assert_eq!(list(&conn, 10, 0, &ListFilter::default()).unwrap()[0].title.as_deref(), Some("prod DB connection string"));
+
+        // The title survives a trip through the trash
+        trash_many(&mut conn, &[id]).unwrap();
+        assert_eq!(trashed(&conn).unwrap()[0].0.title.as_deref(), Some("prod DB connection string"));
+        restore(&conn, id).unwrap();
+        set_title(&conn, id, None).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().title, None);
+        assert!(set_title(&conn, 999, Some("x")).is_err());
+    }
+
     #[test]
     fn test_update_label_nonexistent() {
         let conn = test_conn();
@@ -441,21 +1991,322 @@ mod tests {
     }
 
     #[test]
-    fn test_clear() {
+    fn test_delete_many() {
+        let mut conn = test_conn();
+        let a = insert(&conn, &sample_entry("a")).unwrap();
+        let b = insert(&conn, &sample_entry("b")).unwrap();
+        let c = insert(&conn, &sample_entry("c")).unwrap();
+        delete_many(&mut conn, &[a, c]).unwrap();
+        assert!(get_by_id(&conn, a).is_err());
+        assert!(get_by_id(&conn, b).is_ok());
+        assert!(get_by_id(&conn, c).is_err());
+    }
+
+    #[test]
+    fn test_delete_many_missing_id_rolls_back() {
+        let mut conn = test_conn();
+        let a = insert(&conn, &sample_entry("a")).unwrap();
+        let err = delete_many(&mut conn, &[a, 999]).unwrap_err();
+        assert!(matches!(err, ClipmError::NotFound(_)));
+        assert!(get_by_id(&conn, a).is_ok());
+    }
+
+    #[test]
+    fn test_enforce_quota_by_count() {
         let conn = test_conn();
-        insert(&conn, &sample_entry("one")).unwrap();
-        insert(&conn, &sample_entry("two")).unwrap();
-        let count = clear(&conn).unwrap();
-        assert_eq!(count, 2);
-        let entries = list(&conn, 10, 0, None, None, None).unwrap();
-        assert!(entries.is_empty());
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let mut entry = sample_entry(&format!("scratch {i}"));
+            entry.label = Some("scratch".to_string());
+            ids.push(insert(&conn, &entry).unwrap());
+        }
+        let mut curated = sample_entry("keep me");
+        curated.label = Some("snippets".to_string());
+        let curated_id = insert(&conn, &curated).unwrap();
+
+        assert_eq!(enforce_quota(&conn, "scratch", Some(3), None).unwrap(), 2);
+        assert!(get_by_id(&conn, ids[0]).is_err());
+        assert!(get_by_id(&conn, ids[1]).is_err());
+        assert!(get_by_id(&conn, ids[2]).is_ok());
+        assert!(get_by_id(&conn, ids[4]).is_ok());
+        assert!(get_by_id(&conn, curated_id).is_ok());
+    }
+
+    #[test]
+    fn test_enforce_quota_by_bytes_keeps_newest() {
+        let conn = test_conn();
+        let mut ids = Vec::new();
+        for content in ["aaaa", "bbbb", "cccccccccc"] {
+            let mut entry = sample_entry(content);
+            entry.label = Some("scratch".to_string());
+            ids.push(insert(&conn, &entry).unwrap());
+        }
+        // The newest entry alone exceeds the limit but is never evicted
+        assert_eq!(enforce_quota(&conn, "scratch", None, Some(8)).unwrap(), 2);
+        assert!(get_by_id(&conn, ids[2]).is_ok());
+        assert!(get_by_id(&conn, ids[1]).is_err());
+    }
+
+    #[test]
+    fn test_prune_candidates_by_age() {
+        let conn = test_conn();
+        let now = chrono::Utc::now();
+        let old = insert(&conn, &sample_entry_at("old", &(now - chrono::Duration::days(40)).to_rfc3339())).unwrap();
+        insert(&conn, &sample_entry_at("new", &now.to_rfc3339())).unwrap();
+        assert_eq!(prune_candidates(&conn, Some(30), None, None).unwrap(), vec![old]);
+    }
+
+    #[test]
+    fn test_expire_passwords() {
+        let mut conn = test_conn();
+        let now = chrono::Utc::now();
+        let mut old_password = sample_entry_at("hunter2", &(now - chrono::Duration::hours(30)).to_rfc3339());
+        old_password.content_type = ContentType::Password;
+        let mut new_password = sample_entry_at("correct horse", &now.to_rfc3339());
+        new_password.content_type = ContentType::Password;
+        insert(&conn, &old_password).unwrap();
+        let kept = insert(&conn, &new_password).unwrap();
+        let old_text = insert(&conn, &sample_entry_at("old text", &(now - chrono::Duration::hours(30)).to_rfc3339())).unwrap();
+
+        let cutoff = (now - chrono::Duration::hours(24)).to_rfc3339();
+        assert_eq!(expire_passwords(&mut conn, &cutoff).unwrap(), 1);
+        let ids: Vec<i64> = list(&conn, 10, 0, &ListFilter::default()).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![old_text, kept]);
+        assert_eq!(expire_passwords(&mut conn, &cutoff).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_prune_candidates_by_count_and_size() {
+        let conn = test_conn();
+        let a = insert(&conn, &sample_entry("aaaa")).unwrap();
+        let b = insert(&conn, &sample_entry("bbbb")).unwrap();
+        insert(&conn, &sample_entry("cccc")).unwrap();
+        assert_eq!(prune_candidates(&conn, None, Some(2), None).unwrap(), vec![a]);
+        assert_eq!(prune_candidates(&conn, None, None, Some(5)).unwrap(), vec![a, b]);
+        assert!(prune_candidates(&conn, None, None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_with_write_tx_rolls_back_on_error() {
+        let mut conn = test_conn();
+        let result: Result<(), ClipmError> = with_write_tx(&mut conn, |tx| {
+            insert(tx, &sample_entry("rolled back"))?;
+            Err(ClipmError::InvalidInput("abort".into()))
+        });
+        assert!(result.is_err());
+        assert!(list(&conn, 10, 0, &ListFilter::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let path = std::env::temp_dir().join(format!("clipm-concurrent-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        open_at(&path).unwrap();
+
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut conn = open_at(&path).unwrap();
+                    // No SQLite-level waiting: contention must be absorbed by with_write_tx
+                    conn.busy_timeout(Duration::from_millis(0)).unwrap();
+                    for i in 0..10 {
+                        with_write_tx(&mut conn, |tx| insert(tx, &sample_entry(&format!("w{w} e{i}")))).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let conn = open_at(&path).unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM clips", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 40);
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_pinned_entries_survive_clear_and_retention() {
+        let mut conn = test_conn();
+        let old = (chrono::Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+        let mut password = sample_entry_at("hunter2", &old);
+        password.content_type = ContentType::Password;
+        let pinned_password = insert(&conn, &password).unwrap();
+        let pinned = insert(&conn, &sample_entry_at("keep me", &old)).unwrap();
+        let other = insert(&conn, &sample_entry_at("old", &old)).unwrap();
+        set_pinned(&conn, pinned_password, true).unwrap();
+        set_pinned(&conn, pinned, true).unwrap();
+        assert!(matches!(set_pinned(&conn, 999, true), Err(ClipmError::NotFound(_))));
+
+        assert_eq!(prune_candidates(&conn, Some(30), None, None).unwrap(), vec![other]);
+        assert_eq!(expire_passwords(&mut conn, &chrono::Utc::now().to_rfc3339()).unwrap(), 0);
+        let filter = ListFilter { pinned: true, ..Default::default() };
+        let ids: Vec<i64> = list(&conn, 10, 0, &filter).unwrap().iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![pinned, pinned_password]);
+
+        assert_eq!(trash_all(&conn).unwrap(), 1);
+        assert_eq!(pinned_count(&conn).unwrap(), 2);
+        set_pinned(&conn, pinned, false).unwrap();
+        assert_eq!(trash_all(&conn).unwrap(), 1);
+        assert_eq!(list(&conn, 10, 0, &ListFilter::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_entries_between() {
+        let conn = test_conn();
+        let a = insert(&conn, &sample_entry_at("a", "2026-02-17T09:12:00+00:00")).unwrap();
+        insert(&conn, &sample_entry_at("next day", "2026-02-18T00:00:00+00:00")).unwrap();
+        let b = insert(&conn, &sample_entry_at("b", "2026-02-17T08:00:00+00:00")).unwrap();
+        insert(&conn, &sample_entry_at("day before", "2026-02-16T23:59:59+00:00")).unwrap();
+        let ids: Vec<i64> = entries_between(&conn, "2026-02-17T00:00:00+00:00", "2026-02-18T00:00:00+00:00")
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![b, a]);
+    }
+
+    #[test]
+    fn test_count_since_and_oldest_unpinned() {
+        let conn = test_conn();
+        let first = insert(&conn, &sample_entry_at("first", "2026-02-17T08:00:00+00:00")).unwrap();
+        let mut password = sample_entry_at("hunter2", "2026-02-17T09:00:00+00:00");
+        password.content_type = ContentType::Password;
+        insert(&conn, &password).unwrap();
+        insert(&conn, &sample_entry_at("third", "2026-02-17T10:00:00+00:00")).unwrap();
+        set_pinned(&conn, first, true).unwrap();
+
+        assert_eq!(count_since(&conn, "2026-02-17T09:00:00+00:00").unwrap(), 2);
+        assert_eq!(oldest_unpinned(&conn, None).unwrap().as_deref(), Some("2026-02-17T09:00:00+00:00"));
+        assert_eq!(oldest_unpinned(&conn, Some(&ContentType::Password)).unwrap().as_deref(), Some("2026-02-17T09:00:00+00:00"));
+        assert_eq!(oldest_unpinned(&conn, Some(&ContentType::Image)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_trash_restore_and_undo() {
+        let mut conn = test_conn();
+        let a = insert(&conn, &sample_entry("one")).unwrap();
+        let b = insert(&conn, &sample_entry("two")).unwrap();
+        let c = insert(&conn, &sample_entry("three")).unwrap();
+
+        trash_many(&mut conn, &[a]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        trash_many(&mut conn, &[b, c]).unwrap();
+        assert!(list(&conn, 10, 0, &ListFilter::default()).unwrap().is_empty());
+        assert!(search(&conn, "two", 10, 0, &SearchFilter::default()).unwrap().is_empty());
+        let trashed_ids: Vec<i64> = trashed(&conn).unwrap().iter().map(|(e, _)| e.id).collect();
+        assert_eq!(trashed_ids, vec![c, b, a]);
+
+        // Undo brings back the last delete only, under the same IDs
+        assert_eq!(restore_last(&conn).unwrap(), vec![b, c]);
+        assert_eq!(get_by_id(&conn, c).unwrap().content, "three");
+        assert_eq!(search(&conn, "two", 10, 0, &SearchFilter::default()).unwrap().len(), 1);
+
+        restore(&conn, a).unwrap();
+        assert!(matches!(restore(&conn, a), Err(ClipmError::NotFound(_))));
+        assert!(restore_last(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_merge() {
+        let conn = test_conn();
+        let oldest = insert(&conn, &sample_entry("same")).unwrap();
+        let other = insert(&conn, &sample_entry("other")).unwrap();
+        let older = insert(&conn, &sample_entry("same")).unwrap();
+        let newest = insert(&conn, &sample_entry("same")).unwrap();
+        update_label(&conn, oldest, Some("kept")).unwrap();
+        set_pinned(&conn, oldest, true).unwrap();
+        set_trigger(&conn, older, Some(";s")).unwrap();
+        record_use(&conn, older).unwrap();
+
+        let groups = duplicates(&conn).unwrap();
+        assert_eq!(groups, [(newest, vec![older, oldest])]);
+        assert_eq!(merge_duplicates(&conn, newest, &groups[0].1).unwrap(), 8);
+
+        let merged = get_summary(&conn, newest).unwrap();
+        assert_eq!(merged.label.as_deref(), Some("kept"));
+        assert_eq!(merged.usage.count, 1);
+        assert_eq!(get_by_trigger(&conn, ";s").unwrap().id, newest);
+        assert_eq!(pinned_count(&conn).unwrap(), 1);
+        assert_eq!(count(&conn).unwrap(), 2);
+        assert!(get_summary(&conn, other).is_ok());
+        assert!(duplicates(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_triggers() {
+        let mut conn = test_conn();
+        let addr = insert(&conn, &sample_entry("1 Main St")).unwrap();
+        let sig = insert(&conn, &sample_entry("Regards")).unwrap();
+        set_trigger(&conn, addr, Some(";addr")).unwrap();
+        set_trigger(&conn, sig, Some(";sig")).unwrap();
+        assert!(matches!(set_trigger(&conn, sig, Some(";addr")), Err(ClipmError::InvalidInput(_))));
+        // Setting an entry's own trigger again is fine
+        set_trigger(&conn, addr, Some(";addr")).unwrap();
+        assert_eq!(get_by_trigger(&conn, ";addr").unwrap().content, "1 Main St");
+        let listed: Vec<(String, i64)> = triggers(&conn).unwrap().into_iter().map(|(t, e)| (t, e.id)).collect();
+        assert_eq!(listed, vec![(";addr".to_string(), addr), (";sig".to_string(), sig)]);
+
+        // A restored entry gives up a trigger that was reassigned meanwhile
+        trash_many(&mut conn, &[addr]).unwrap();
+        set_trigger(&conn, sig, Some(";addr")).unwrap();
+        restore(&conn, addr).unwrap();
+        assert_eq!(get_by_trigger(&conn, ";addr").unwrap().id, sig);
+
+        set_trigger(&conn, sig, None).unwrap();
+        assert!(matches!(get_by_trigger(&conn, ";addr"), Err(ClipmError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_keychain_orphans() {
+        let mut conn = test_conn();
+        let orphans = |conn: &Connection| -> Vec<String> {
+            conn.prepare("SELECT account FROM keychain_orphans ORDER BY account").unwrap()
+                .query_map([], |r| r.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        let mut ids = Vec::new();
+        for account in ["entry-a", "entry-b"] {
+            let id = insert(&conn, &sample_entry("")).unwrap();
+            conn.execute("UPDATE clips SET keychain_account = ?1 WHERE id = ?2", params![account, id]).unwrap();
+            ids.push(id);
+        }
+
+        // Trash and restore keep the item; only deleting for good orphans it
+        trash_many(&mut conn, &ids).unwrap();
+        restore(&conn, ids[0]).unwrap();
+        assert!(orphans(&conn).is_empty());
+        delete(&conn, ids[0]).unwrap();
+        empty_trash(&conn).unwrap();
+        assert_eq!(orphans(&conn), vec!["entry-a", "entry-b"]);
+    }
+
+    #[test]
+    fn test_trash_many_missing_id_rolls_back() {
+        let mut conn = test_conn();
+        let a = insert(&conn, &sample_entry("one")).unwrap();
+        assert!(matches!(trash_many(&mut conn, &[a, 999]), Err(ClipmError::NotFound(_))));
+        assert!(get_by_id(&conn, a).is_ok());
+        assert!(trashed(&conn).unwrap().is_empty());
     }
 
     #[test]
-    fn test_clear_empty() {
+    fn test_trash_all_empty_and_purge() {
         let conn = test_conn();
-        let count = clear(&conn).unwrap();
-        assert_eq!(count, 0);
+        insert(&conn, &sample_entry("one")).unwrap();
+        let pinned = insert(&conn, &sample_entry("two")).unwrap();
+        set_pinned(&conn, pinned, true).unwrap();
+        assert_eq!(trash_all(&conn).unwrap(), 1);
+        assert_eq!(count(&conn).unwrap(), 1);
+
+        assert_eq!(purge_trash(&conn, "2000-01-01T00:00:00+00:00").unwrap(), 0);
+        assert_eq!(purge_trash(&conn, &(chrono::Utc::now() + chrono::Duration::seconds(1)).to_rfc3339()).unwrap(), 1);
+        trash_all(&conn).unwrap();
+        assert_eq!(empty_trash(&conn).unwrap(), 0);
+        assert_eq!(count(&conn).unwrap(), 1);
     }
 
     #[test]
@@ -463,16 +2314,61 @@ mod tests {
         let conn = test_conn();
         insert(&conn, &sample_entry("hello world")).unwrap();
         insert(&conn, &sample_entry("goodbye world")).unwrap();
-        let results = search(&conn, "hello", 10, None, None).unwrap();
+        let results = search(&conn, "hello", 10, 0, &SearchFilter::default()).unwrap();
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].content, "hello world");
+        assert_eq!(results[0].preview, "hello world");
+    }
+
+    #[test]
+    fn test_reindex() {
+        let conn = test_conn();
+        insert(&conn, &sample_entry("running late")).unwrap();
+        let mut secret = sample_entry("hunter2");
+        secret.content_type = ContentType::Password;
+        insert(&conn, &secret).unwrap();
+        let filter = SearchFilter::default();
+        assert!(search(&conn, "run", 10, 0, &filter).unwrap().is_empty());
+
+        let mut calls = Vec::new();
+        assert_eq!(reindex(&conn, Some("porter unicode61"), |done, total| calls.push((done, total))).unwrap(), 2);
+        assert_eq!(calls, [(2, 2)]);
+        assert_eq!(search(&conn, "run", 10, 0, &filter).unwrap().len(), 1);
+        assert!(search(&conn, "hunter2", 10, 0, &filter).unwrap().is_empty());
+        // The sync triggers keep working on the recreated table
+        insert(&conn, &sample_entry("runs daily")).unwrap();
+        assert_eq!(search(&conn, "run", 10, 0, &filter).unwrap().len(), 2);
+
+        assert_eq!(reindex(&conn, None, |_, _| {}).unwrap(), 3);
+        assert_eq!(search(&conn, "run", 10, 0, &filter).unwrap().len(), 2);
+        assert!(matches!(reindex(&conn, Some("x'); DROP TABLE clips; --"), |_, _| {}), Err(ClipmError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_search_offset_and_count() {
+        let conn = test_conn();
+        for i in 0..5 {
+            insert(&conn, &sample_entry(&format!("match {i}"))).unwrap();
+        }
+        insert(&conn, &sample_entry("other")).unwrap();
+        let first = search(&conn, "match", 2, 0, &SearchFilter::default()).unwrap();
+        let second = search(&conn, "match", 2, 2, &SearchFilter::default()).unwrap();
+        let last = search(&conn, "match", 2, 4, &SearchFilter::default()).unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 2);
+        assert_eq!(last.len(), 1);
+        let mut ids: Vec<i64> = first.iter().chain(&second).chain(&last).map(|e| e.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(search_count(&conn, "match", &SearchFilter::default()).unwrap(), 5);
+        assert_eq!(search_count(&conn, "nothing", &SearchFilter::default()).unwrap(), 0);
     }
 
     #[test]
     fn test_search_no_results() {
         let conn = test_conn();
         insert(&conn, &sample_entry("hello world")).unwrap();
-        let results = search(&conn, "nonexistent", 10, None, None).unwrap();
+        let results = search(&conn, "nonexistent", 10, 0, &SearchFilter::default()).unwrap();
         assert!(results.is_empty());
     }
 
@@ -480,14 +2376,14 @@ mod tests {
     fn test_search_special_chars() {
         let conn = test_conn();
         insert(&conn, &sample_entry("hello \"world\"")).unwrap();
-        let results = search(&conn, "hello", 10, None, None).unwrap();
+        let results = search(&conn, "hello", 10, 0, &SearchFilter::default()).unwrap();
         assert_eq!(results.len(), 1);
     }
 
     #[test]
     fn test_search_empty_query() {
         let conn = test_conn();
-        let err = search(&conn, "   ", 10, None, None).unwrap_err();
+        let err = search(&conn, "   ", 10, 0, &SearchFilter::default()).unwrap_err();
         assert!(matches!(err, ClipmError::InvalidInput(_)));
     }
 
@@ -497,7 +2393,192 @@ mod tests {
         migrate(&conn).unwrap();
         migrate(&conn).unwrap();
         let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
-        assert_eq!(version, 2);
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_concurrent_first_open_migrates_once() {
+        let path = std::env::temp_dir().join(format!("clipm-first-run-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(4));
+
+        let openers: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    open_at(&path).map(|_| ())
+                })
+            })
+            .collect();
+        for opener in openers {
+            opener.join().unwrap().unwrap();
+        }
+
+        let conn = open_at(&path).unwrap();
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_open_team_at() {
+        let path = std::env::temp_dir().join(format!("clipm-team-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(open_team_at(&path), Err(ClipmError::Config(_))));
+
+        let conn = open_at(&path).unwrap();
+        insert(&conn, &sample_entry("deploy snippet")).unwrap();
+        drop(conn);
+
+        let team = open_team_at(&path).unwrap();
+        assert_eq!(search(&team, "deploy", 10, 0, &SearchFilter::default()).unwrap().len(), 1);
+        assert!(insert(&team, &sample_entry("not allowed")).is_err());
+        drop(team);
+
+        Connection::open(&path).unwrap().execute_batch("PRAGMA user_version = 3;").unwrap();
+        assert!(matches!(open_team_at(&path), Err(ClipmError::Config(_))));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_insert_caches_preview() {
+        let conn = test_conn();
+        let id = insert(&conn, &sample_entry("first line\nsecond line")).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().preview, "first line second line");
+
+        let mut secret = sample_entry("hunter2");
+        secret.content_type = ContentType::Password;
+        let id = insert(&conn, &secret).unwrap();
+        assert_eq!(get_by_id(&conn, id).unwrap().preview, "");
+    }
+
+    #[test]
+    fn test_image_entry_round_trip() {
+        let conn = test_conn();
+        let text_id = insert(&conn, &sample_entry("")).unwrap();
+        let mut entry = sample_entry("");
+        entry.content_type = ContentType::Image;
+        entry.image = Some(ClipImage { width: 2, height: 1, png: vec![0x89, b'P', b'N', b'G'] });
+        entry.byte_size = 4;
+        let id = insert(&conn, &entry).unwrap();
+
+        assert_eq!(get_by_id(&conn, id).unwrap().image, entry.image);
+        assert_eq!(get_by_id(&conn, text_id).unwrap().image, None);
+        assert_eq!(get_summary(&conn, id).unwrap().image_size, Some((2, 1)));
+        assert_eq!(list(&conn, 10, 0, &ListFilter { content_type: Some(ContentType::Image), ..Default::default() }).unwrap().len(), 1);
+
+        assert!(is_duplicate_image(&conn, &[0x89, b'P', b'N', b'G']).unwrap());
+        assert!(!is_duplicate_image(&conn, b"other").unwrap());
+        // An image's empty content must not look like a repeat of empty text
+        assert!(!is_duplicate(&conn, "").unwrap());
+    }
+
+    #[test]
+    fn test_migration_backfills_preview() {
+        let conn = test_conn();
+        conn.execute_batch(
+            "INSERT INTO clips (content, content_type, byte_size, created_at) VALUES ('a' || char(10) || 'b', 'text', 3, '2026-01-01T00:00:00Z');
+             INSERT INTO clips (content, content_type, byte_size, created_at) VALUES ('secret', 'password', 6, '2026-01-01T00:00:00Z');
+             ALTER TABLE clips DROP COLUMN content_html;
+             DROP INDEX idx_clips_content_hash;
+             ALTER TABLE clips DROP COLUMN content_hash;
+             DROP TRIGGER clips_keychain_orphan;
+             DROP TRIGGER trash_keychain_orphan;
+             DROP TABLE keychain_orphans;
+             ALTER TABLE clips DROP COLUMN keychain_account;
+             ALTER TABLE clips DROP COLUMN preview;
+             ALTER TABLE clips DROP COLUMN image;
+             ALTER TABLE clips DROP COLUMN image_width;
+             ALTER TABLE clips DROP COLUMN image_height;
+             ALTER TABLE clips DROP COLUMN cwd;
+             ALTER TABLE clips DROP COLUMN git_branch;
+             ALTER TABLE clips DROP COLUMN tty;
+             DROP INDEX idx_clips_session;
+             ALTER TABLE clips DROP COLUMN session_id;
+             DROP TABLE sessions;
+             ALTER TABLE clips DROP COLUMN pinned;
+             DROP TABLE trash;
+             DROP INDEX idx_clips_trigger;
+             ALTER TABLE clips DROP COLUMN trigger;
+             DROP TABLE stats;
+             ALTER TABLE clips DROP COLUMN use_count;
+             ALTER TABLE clips DROP COLUMN last_used_at;
+             ALTER TABLE clips DROP COLUMN title;
+             PRAGMA user_version = 3;"
+        ).unwrap();
+        migrate(&conn).unwrap();
+        let previews: Vec<String> = conn.prepare("SELECT preview FROM clips ORDER BY id").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(previews, vec!["a b".to_string(), String::new()]);
+        let hashes: Vec<Option<String>> = conn.prepare("SELECT content_hash FROM clips ORDER BY id").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(hashes, vec![Some(content_hash("a\nb", None)), None]);
+    }
+
+    #[test]
+    fn test_next_counter_increments_per_name() {
+        let conn = test_conn();
+        assert_eq!(next_counter(&conn, "invoice").unwrap(), 1);
+        assert_eq!(next_counter(&conn, "invoice").unwrap(), 2);
+        assert_eq!(next_counter(&conn, "ticket").unwrap(), 1);
+        assert_eq!(next_counter(&conn, "invoice").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_list_by_provenance() {
+        let conn = test_conn();
+        let mut in_repo = sample_entry("in repo");
+        in_repo.provenance = Provenance { cwd: Some("/src/clipm".into()), git_branch: Some("main".into()), tty: Some("/dev/ttys003".into()), session_id: None };
+        let in_repo = insert(&conn, &in_repo).unwrap();
+        let mut below = sample_entry("below");
+        below.provenance.cwd = Some("/src/clipm/docs".into());
+        let below = insert(&conn, &below).unwrap();
+        let mut sibling = sample_entry("sibling");
+        sibling.provenance.cwd = Some("/src/clipmate".into());
+        insert(&conn, &sibling).unwrap();
+        insert(&conn, &sample_entry("nowhere")).unwrap();
+
+        let ids = |filter: &ListFilter| list(&conn, 10, 0, filter).unwrap().iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(&ListFilter { cwd: Some("/src/clipm".into()), ..Default::default() }), vec![below, in_repo]);
+        assert_eq!(ids(&ListFilter { cwd: Some("/src/clipm/".into()), ..Default::default() }), vec![below]);
+        assert_eq!(ids(&ListFilter { branch: Some("main".into()), ..Default::default() }), vec![in_repo]);
+        assert_eq!(get_by_id(&conn, in_repo).unwrap().provenance.tty.as_deref(), Some("/dev/ttys003"));
+    }
+
+    #[test]
+    fn test_sessions() {
+        let conn = test_conn();
+        assert_eq!(current_session(&conn).unwrap(), None);
+        insert(&conn, &sample_entry("before")).unwrap();
+
+        let first = start_session(&conn, Some("debugging prod")).unwrap();
+        let mut entry = sample_entry("during");
+        entry.provenance.session_id = current_session(&conn).unwrap().map(|s| s.id);
+        let during = insert(&conn, &entry).unwrap();
+        let second = start_session(&conn, None).unwrap();
+        assert_eq!(current_session(&conn).unwrap().unwrap().id, second);
+
+        let filter = ListFilter { session: Some(first), ..Default::default() };
+        assert_eq!(list(&conn, 10, 0, &filter).unwrap().iter().map(|e| e.id).collect::<Vec<_>>(), vec![during]);
+        assert_eq!(find_session(&conn, "debugging prod").unwrap().id, first);
+        assert!(matches!(find_session(&conn, "nope"), Err(ClipmError::NotFound(_))));
+
+        let all = sessions(&conn, 10).unwrap();
+        assert_eq!(all.iter().map(|(s, n)| (s.id, *n)).collect::<Vec<_>>(), vec![(second, 0), (first, 1)]);
+        assert!(all[1].0.ended_at.is_some());
+
+        assert_eq!(end_session(&conn).unwrap().unwrap().id, second);
+        assert_eq!(current_session(&conn).unwrap(), None);
+        assert_eq!(end_session(&conn).unwrap(), None);
     }
 
     #[test]
@@ -511,10 +2592,10 @@ mod tests {
         insert(&conn, &sample_entry_at("three days ago", &three_days_ago.to_rfc3339())).unwrap();
         insert(&conn, &sample_entry_at("thirty days ago", &thirty_days_ago.to_rfc3339())).unwrap();
 
-        let entries = list(&conn, 10, 0, None, Some(7), None).unwrap();
+        let entries = list(&conn, 10, 0, &ListFilter { days: Some(7), ..Default::default() }).unwrap();
         assert_eq!(entries.len(), 2);
-        assert_eq!(entries[0].content, "three days ago");
-        assert_eq!(entries[1].content, "today");
+        assert_eq!(entries[0].preview, "three days ago");
+        assert_eq!(entries[1].preview, "today");
     }
 
     #[test]
@@ -535,9 +2616,9 @@ mod tests {
         recent_unlabeled.label = None;
         insert(&conn, &recent_unlabeled).unwrap();
 
-        let entries = list(&conn, 10, 0, Some("important"), Some(7), None).unwrap();
+        let entries = list(&conn, 10, 0, &ListFilter { label: Some("important".into()), days: Some(7), ..Default::default() }).unwrap();
         assert_eq!(entries.len(), 1);
-        assert_eq!(entries[0].content, "recent labeled");
+        assert_eq!(entries[0].preview, "recent labeled");
     }
 
     #[test]
@@ -551,9 +2632,9 @@ mod tests {
         insert(&conn, &sample_entry_at("hello five", &five_days_ago.to_rfc3339())).unwrap();
         insert(&conn, &sample_entry_at("hello old", &twenty_days_ago.to_rfc3339())).unwrap();
 
-        let results = search(&conn, "hello", 10, Some(10), None).unwrap();
+        let results = search(&conn, "hello", 10, 0, &SearchFilter { days: Some(10), ..Default::default() }).unwrap();
         assert_eq!(results.len(), 2);
-        let contents: Vec<String> = results.iter().map(|e| e.content.clone()).collect();
+        let contents: Vec<String> = results.iter().map(|e| e.preview.clone()).collect();
         assert!(contents.contains(&"hello recent".to_string()));
         assert!(contents.contains(&"hello five".to_string()));
         assert!(!contents.contains(&"hello old".to_string()));
@@ -576,20 +2657,98 @@ mod tests {
         let mut entry = sample_entry("my-secret-password");
         entry.content_type = ContentType::Password;
         insert(&conn, &entry).unwrap();
-        let results = search(&conn, "secret", 10, None, None).unwrap();
+        let results = search(&conn, "secret", 10, 0, &SearchFilter::default()).unwrap();
         assert_eq!(results.len(), 0);
     }
 
+    #[test]
+    fn test_set_content_type_resyncs_fts_and_preview() {
+        let conn = test_conn();
+        let id = insert(&conn, &sample_entry("my-secret-password")).unwrap();
+        set_content_type(&conn, id, &ContentType::Password, false).unwrap();
+        assert!(search(&conn, "secret", 10, 0, &SearchFilter::default()).unwrap().is_empty());
+        assert_eq!(get_summary(&conn, id).unwrap().preview, "");
+
+        set_content_type(&conn, id, &ContentType::Text, false).unwrap();
+        assert_eq!(search(&conn, "secret", 10, 0, &SearchFilter::default()).unwrap()[0].preview, "my-secret-password");
+        assert!(matches!(set_content_type(&conn, id, &ContentType::Image, false), Err(ClipmError::InvalidInput(_))));
+        assert!(matches!(set_content_type(&conn, 999, &ContentType::Text, false), Err(ClipmError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_set_content_type_moves_password_into_keychain() {
+        let mut conn = test_conn();
+        let id = insert(&conn, &sample_entry("hunter2")).unwrap();
+        with_write_tx(&mut conn, |tx| set_content_type(tx, id, &ContentType::Password, true)).unwrap();
+
+        let (content, account): (String, String) = conn
+            .query_row("SELECT content, keychain_account FROM clips WHERE id = ?1", params![id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(content, "");
+        assert_eq!(BASE64.decode(keychain::get(&account).unwrap().as_bytes()).unwrap(), b"hunter2");
+        assert_eq!(get_by_id(&conn, id).unwrap().content, "hunter2");
+    }
+
+    #[test]
+    fn test_set_content_type_moves_password_out_of_keychain() {
+        let mut conn = test_conn();
+        let mut secret = sample_entry("hunter2");
+        secret.content_type = ContentType::Password;
+        let id = with_write_tx(&mut conn, |tx| insert_secret(tx, &secret)).unwrap();
+        let account: String = conn.query_row("SELECT keychain_account FROM clips WHERE id = ?1", params![id], |r| r.get(0)).unwrap();
+        with_write_tx(&mut conn, |tx| set_content_type(tx, id, &ContentType::Text, true)).unwrap();
+
+        let (content, left): (String, Option<String>) = conn
+            .query_row("SELECT content, keychain_account FROM clips WHERE id = ?1", params![id], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!((content.as_str(), left), ("hunter2", None));
+        assert_eq!(search(&conn, "hunter2", 10, 0, &SearchFilter::default()).unwrap().len(), 1);
+        sweep_keychain(&conn).unwrap();
+        assert!(keychain::get(&account).is_none());
+    }
+
+    #[test]
+    fn test_detected_types() {
+        let conn = test_conn();
+        insert(&conn, &sample_entry("plain words")).unwrap();
+        let url = insert(&conn, &sample_entry("https://example.com")).unwrap();
+        let mut json = sample_entry("{\"ok\": true}");
+        json.content_type = ContentType::Code;
+        let json = insert(&conn, &json).unwrap();
+        let mut password = sample_entry("https://user:pw@example.com");
+        password.content_type = ContentType::Password;
+        insert(&conn, &password).unwrap();
+        assert_eq!(detected_types(&conn).unwrap(), vec![(url, ContentType::Url), (json, ContentType::Json)]);
+    }
+
+    #[test]
+    fn test_scan_withholds_password_content() {
+        let conn = test_conn();
+        let mut password = sample_entry("hunter2");
+        password.content_type = ContentType::Password;
+        password.label = Some("work/vpn".to_string());
+        let secret = insert(&conn, &password).unwrap();
+        let text = insert(&conn, &sample_entry("notes")).unwrap();
+
+        let all = scan(&conn, &SearchFilter::default()).unwrap();
+        let found: Vec<(i64, Option<String>)> = all.into_iter().map(|(s, c)| (s.id, c)).collect();
+        assert_eq!(found, vec![(text, Some("notes".to_string())), (secret, None)]);
+
+        let filter = SearchFilter { label: Some("work".to_string()), ..Default::default() };
+        assert_eq!(scan(&conn, &filter).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_password_label_in_fts() {
         let conn = test_conn();
         let mut entry = sample_entry("my-secret-password");
         entry.content_type = ContentType::Password;
         entry.label = Some("github-token".to_string());
-        insert(&conn, &entry).unwrap();
-        let results = search(&conn, "github", 10, None, None).unwrap();
+        let id = insert(&conn, &entry).unwrap();
+        let results = search(&conn, "github", 10, 0, &SearchFilter::default()).unwrap();
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].content, "my-secret-password");
+        assert_eq!(results[0].id, id);
+        assert_eq!(results[0].preview, "");
     }
 
     #[test]
@@ -598,15 +2757,15 @@ mod tests {
         insert(&conn, &sample_entry("text content")).unwrap();
         let mut pass_entry = sample_entry("password123");
         pass_entry.content_type = ContentType::Password;
-        insert(&conn, &pass_entry).unwrap();
+        let pass_id = insert(&conn, &pass_entry).unwrap();
 
-        let text_entries = list(&conn, 10, 0, None, None, Some("text")).unwrap();
+        let text_entries = list(&conn, 10, 0, &ListFilter { content_type: Some(ContentType::Text), ..Default::default() }).unwrap();
         assert_eq!(text_entries.len(), 1);
-        assert_eq!(text_entries[0].content, "text content");
+        assert_eq!(text_entries[0].preview, "text content");
 
-        let pass_entries = list(&conn, 10, 0, None, None, Some("password")).unwrap();
+        let pass_entries = list(&conn, 10, 0, &ListFilter { content_type: Some(ContentType::Password), ..Default::default() }).unwrap();
         assert_eq!(pass_entries.len(), 1);
-        assert_eq!(pass_entries[0].content, "password123");
+        assert_eq!(pass_entries[0].id, pass_id);
     }
 
     #[test]
@@ -619,14 +2778,80 @@ mod tests {
         let mut pass_entry = sample_entry("secret123");
         pass_entry.content_type = ContentType::Password;
         pass_entry.label = Some("greeting".to_string());
-        insert(&conn, &pass_entry).unwrap();
+        let pass_id = insert(&conn, &pass_entry).unwrap();
 
-        let text_results = search(&conn, "greeting", 10, None, Some("text")).unwrap();
+        let text_results = search(&conn, "greeting", 10, 0, &SearchFilter { content_type: Some(ContentType::Text), ..Default::default() }).unwrap();
         assert_eq!(text_results.len(), 1);
-        assert_eq!(text_results[0].content, "hello world");
+        assert_eq!(text_results[0].preview, "hello world");
 
-        let pass_results = search(&conn, "greeting", 10, None, Some("password")).unwrap();
+        let pass_results = search(&conn, "greeting", 10, 0, &SearchFilter { content_type: Some(ContentType::Password), ..Default::default() }).unwrap();
         assert_eq!(pass_results.len(), 1);
-        assert_eq!(pass_results[0].content, "secret123");
+        assert_eq!(pass_results[0].id, pass_id);
+    }
+
+    #[test]
+    fn test_content_hashes() {
+        let conn = test_conn();
+        insert(&conn, &sample_entry("hello")).unwrap();
+        let hashes = content_hashes(&conn).unwrap();
+        assert!(hashes.contains(&content_hash("hello", None)));
+        assert!(!hashes.contains(&content_hash("other", None)));
+        let image = ClipImage { width: 1, height: 1, png: b"hello".to_vec() };
+        assert_eq!(content_hash("", Some(&image)), content_hash("hello", None));
+    }
+
+    #[test]
+    fn test_content_hashes_cover_an_export() {
+        let mut conn = test_conn();
+        insert(&conn, &sample_entry("hello")).unwrap();
+        let mut secret = sample_entry("hunter2");
+        secret.content_type = ContentType::Password;
+        with_write_tx(&mut conn, |tx| insert_secret(tx, &secret)).unwrap();
+
+        // What `import` would find already in history after `export`
+        let json = crate::export::to_json(&export(&conn, &ExportFilter::default()).unwrap()).unwrap();
+        let hashes = content_hashes(&conn).unwrap();
+        let entries = crate::export::from_json(json.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| hashes.contains(&content_hash(&e.content, e.image.as_ref()))));
+    }
+
+    #[test]
+    fn test_export_filters() {
+        let conn = test_conn();
+        let mut work = sample_entry("work note");
+        work.label = Some("work".to_string());
+        let work = insert(&conn, &work).unwrap();
+        let mut password = sample_entry("hunter2");
+        password.content_type = ContentType::Password;
+        let password = insert(&conn, &password).unwrap();
+        let other = insert(&conn, &sample_entry("other")).unwrap();
+
+        let ids = |filter: &ExportFilter| export(&conn, filter).unwrap().iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(&ExportFilter::default()), vec![work, password, other]);
+        assert_eq!(ids(&ExportFilter { exclude_passwords: true, ..Default::default() }), vec![work, other]);
+        assert_eq!(ids(&ExportFilter { label: Some("work".into()), ..Default::default() }), vec![work]);
+        assert_eq!(ids(&ExportFilter { content_type: Some(ContentType::Password), ..Default::default() }), vec![password]);
+        assert_eq!(export(&conn, &ExportFilter::default()).unwrap()[1].content, "hunter2");
+        assert_eq!(ids(&ExportFilter { query: Some("note".into()), ..Default::default() }), vec![work]);
+        assert_eq!(ids(&ExportFilter { query: Some("note".into()), label: Some("other".into()), ..Default::default() }), Vec::<i64>::new());
+        assert!(export(&conn, &ExportFilter { query: Some("  ".into()), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_search_within_label_scope() {
+        let conn = test_conn();
+        let mut ids = Vec::new();
+        for label in ["work", "work/projA", "workshop", "Work/projA", "home"] {
+            let mut entry = sample_entry(&format!("migration notes for {label}"));
+            entry.label = Some(label.to_string());
+            ids.push(insert(&conn, &entry).unwrap());
+        }
+
+        let mut scoped: Vec<i64> = search(&conn, "migration", 10, 0, &SearchFilter { label: Some("work".to_string()), ..Default::default() }).unwrap().iter().map(|e| e.id).collect();
+        scoped.sort();
+        assert_eq!(scoped, vec![ids[0], ids[1]]);
+        assert_eq!(search_count(&conn, "migration", &SearchFilter { label: Some("work/projA".to_string()), ..Default::default() }).unwrap(), 1);
+        assert_eq!(search_count(&conn, "migration", &SearchFilter::default()).unwrap(), 5);
     }
 }
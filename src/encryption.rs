@@ -0,0 +1,271 @@
+//! Keys for the optional SQLCipher-encrypted history database.
+//!
+//! An encrypted database is recognised by its header: plain SQLite files start
+//! with `SQLite format 3\0`, SQLCipher files are random bytes throughout. The
+//! passphrase comes from `CLIPM_PASSPHRASE`, an age-encrypted key file (with
+//! `[encryption] identity` in config) or the macOS Keychain; `clipm unlock`
+//! puts it in the Keychain and `clipm lock` takes it out again. The `age` and
+//! `age-keygen` programs handle the key file.
+
+use rusqlite::{params, Connection};
+use std::ffi::OsStr;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config::{self, expand_home, EncryptionConfig};
+use crate::generate;
+use crate::i18n::t;
+use crate::keychain;
+use crate::models::{ClipmError, SecretString};
+
+/// Environment variable holding the passphrase, for scripts and systems
+/// without a Keychain.
+pub const PASSPHRASE_ENV: &str = "CLIPM_PASSPHRASE";
+
+/// Keychain account of the passphrase; each profile's history has its own.
+fn keychain_account() -> Result<String, ClipmError> {
+    Ok(match config::active_profile()? {
+        Some(name) => format!("profiles/{name}/history.db"),
+        None => "history.db".to_string(),
+    })
+}
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether `path` holds an encrypted database. Missing and empty files are not
+/// encrypted: nothing has been written yet.
+pub fn is_encrypted(path: &Path) -> Result<bool, ClipmError> {
+    let mut header = [0u8; 16];
+    let read = match std::fs::File::open(path) {
+        Ok(mut file) => file.read(&mut header)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(read > 0 && &header != SQLITE_HEADER)
+}
+
+/// The passphrase for an encrypted database, or `Locked` if none is available.
+pub fn passphrase() -> Result<SecretString, ClipmError> {
+    if let Some(pass) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+        return Ok(SecretString::new(pass));
+    }
+    if let Some(identity) = &config::load()?.encryption.identity {
+        let key_file = config::key_file_path()?;
+        if key_file.exists() {
+            return open_key_file(identity, &key_file).map(SecretString::new);
+        }
+    }
+    keychain_get().ok_or(ClipmError::Locked)
+}
+
+/// A fresh random key for databases whose key lives in a key file: 32 bytes
+/// from the system's CSPRNG, hex-encoded.
+pub fn random_key() -> Result<String, ClipmError> {
+    Ok(generate::hex(&generate::random_bytes(32)?))
+}
+
+/// Run an age tool, feeding `input` on stdin, and return its stdout.
+fn run_age(program: &str, args: &[&OsStr], input: &[u8]) -> Result<Vec<u8>, ClipmError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ClipmError::Io(t!("Cannot run `{program}`: {error}", program = program, error = e)))?;
+    // Feed stdin from a thread: age streams its output, so writing a large
+    // input while nobody reads stdout would block both sides
+    let stdin = child.stdin.take();
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.map_or(Ok(()), |mut stdin| stdin.write_all(&input)));
+    let output = child.wait_with_output()?;
+    let written = writer.join().unwrap_or(Ok(()));
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(ClipmError::Io(t!("`{program}` failed: {error}", program = program, error = stderr)));
+    }
+    written?;
+    Ok(output.stdout)
+}
+
+/// Whether `data` is an age-encrypted file (binary or armored).
+pub fn is_age_file(data: &[u8]) -> bool {
+    data.starts_with(b"age-encryption.org/") || data.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----")
+}
+
+/// Decrypt an age file with the identity at `identity`.
+pub fn decrypt_with(identity: &Path, data: &[u8]) -> Result<Vec<u8>, ClipmError> {
+    run_age("age", &[OsStr::new("-d"), OsStr::new("-i"), expand_home(identity).as_os_str()], data)
+}
+
+/// age-encrypt `data` for `recipient`: an `age1…` or SSH public key, or a
+/// file of recipients. `armor` produces PEM-style text instead of binary.
+pub fn encrypt_for(recipient: &str, data: &[u8], armor: bool) -> Result<Vec<u8>, ClipmError> {
+    let flag = if recipient.starts_with("age1") || recipient.starts_with("ssh-") { "-r" } else { "-R" };
+    let mut args = vec![OsStr::new(flag), OsStr::new(recipient)];
+    if armor {
+        args.push(OsStr::new("--armor"));
+    }
+    run_age("age", &args, data)
+}
+
+/// Decrypt the key file with the configured identity.
+fn open_key_file(identity: &Path, key_file: &Path) -> Result<String, ClipmError> {
+    let identity = expand_home(identity);
+    let key = run_age("age", &[OsStr::new("-d"), OsStr::new("-i"), identity.as_os_str(), key_file.as_os_str()], b"")?;
+    String::from_utf8(key)
+        .map(|k| k.trim_end().to_string())
+        .map_err(|_| ClipmError::InvalidInput(t!("The key file {path} is damaged", path = key_file.display())))
+}
+
+/// Encrypt `key` to the configured recipient (or the identity's public key)
+/// and write it to `key_file`.
+pub fn seal_key(config: &EncryptionConfig, key: &str, key_file: &Path) -> Result<(), ClipmError> {
+    let recipient = match (&config.recipient, &config.identity) {
+        (Some(recipient), _) => recipient.clone(),
+        (None, Some(identity)) => {
+            let public = run_age("age-keygen", &[OsStr::new("-y"), expand_home(identity).as_os_str()], b"")?;
+            String::from_utf8_lossy(&public).trim().to_string()
+        }
+        (None, None) => return Err(ClipmError::Config(t!("Set `identity` in [encryption] to use a key file"))),
+    };
+    run_age("age", &[OsStr::new("-r"), OsStr::new(&recipient), OsStr::new("-o"), key_file.as_os_str()], key.as_bytes())?;
+    Ok(())
+}
+
+/// Re-encrypt every page of an open, unlocked database with `new_key`.
+pub fn rekey(conn: &Connection, new_key: &str) -> Result<(), ClipmError> {
+    // SQLCipher can't rekey a WAL database; leave WAL for the duration
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE); PRAGMA journal_mode=DELETE;")?;
+    conn.pragma_update(None, "rekey", new_key)?;
+    conn.execute_batch("PRAGMA journal_mode=WAL;")?;
+    Ok(())
+}
+
+/// Set the key on a freshly opened connection and check it. SQLCipher only
+/// notices a wrong key on the first read.
+pub fn apply_key(conn: &Connection, key: &str) -> Result<(), ClipmError> {
+    conn.pragma_update(None, "key", key)?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|_| ClipmError::InvalidInput(t!("Wrong passphrase for the encrypted history")))
+}
+
+/// Rewrite the plain database at `path` as an encrypted copy and swap it in.
+/// Other clipm processes must not be using the database meanwhile.
+pub fn encrypt_in_place(path: &Path, key: &str) -> Result<(), ClipmError> {
+    let encrypted = path.with_extension("db.encrypting");
+    let _ = std::fs::remove_file(&encrypted);
+    {
+        let conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+        conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", params![encrypted.to_string_lossy(), key])?;
+        // sqlcipher_export copies schema and rows but not user_version
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.execute_batch(&format!("PRAGMA encrypted.user_version = {version}; DETACH DATABASE encrypted;"))?;
+    }
+    std::fs::rename(&encrypted, path)?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+    Ok(())
+}
+
+/// The passphrase saved by `unlock`, if any. Always `None` off macOS.
+fn keychain_get() -> Option<SecretString> {
+    keychain::get(&keychain_account().ok()?)
+}
+
+/// Save the passphrase in the login Keychain, replacing any previous one.
+pub fn keychain_store(pass: &str) -> Result<(), ClipmError> {
+    if !keychain::set(&keychain_account()?, pass)? {
+        return Err(ClipmError::Io(t!("Could not save the passphrase in the Keychain")));
+    }
+    Ok(())
+}
+
+/// Forget the saved passphrase. Succeeds if there was none.
+pub fn keychain_delete() -> Result<(), ClipmError> {
+    if !keychain::delete(&keychain_account()?)? {
+        return Err(ClipmError::Io(t!("Could not remove the passphrase from the Keychain")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_in_place_and_reopen() {
+        let path = std::env::temp_dir().join(format!("clipm-encrypt-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch("CREATE TABLE t (x TEXT); INSERT INTO t VALUES ('secret'); PRAGMA user_version = 7;").unwrap();
+        }
+        assert!(!is_encrypted(&path).unwrap());
+
+        encrypt_in_place(&path, "correct horse").unwrap();
+        assert!(is_encrypted(&path).unwrap());
+        assert!(!std::fs::read(&path).unwrap().windows(6).any(|w| w == b"secret"));
+
+        let conn = Connection::open(&path).unwrap();
+        apply_key(&conn, "correct horse").unwrap();
+        let x: String = conn.query_row("SELECT x FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(x, "secret");
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
+        assert_eq!(version, 7);
+        drop(conn);
+
+        let conn = Connection::open(&path).unwrap();
+        assert!(matches!(apply_key(&conn, "wrong"), Err(ClipmError::InvalidInput(_))));
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rekey() {
+        let path = std::env::temp_dir().join(format!("clipm-rekey-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let conn = Connection::open(&path).unwrap();
+            apply_key(&conn, "old").unwrap();
+            conn.execute_batch("PRAGMA journal_mode=WAL; CREATE TABLE t (x TEXT); INSERT INTO t VALUES ('kept');").unwrap();
+            rekey(&conn, "new").unwrap();
+        }
+        let conn = Connection::open(&path).unwrap();
+        assert!(apply_key(&conn, "old").is_err());
+        drop(conn);
+        let conn = Connection::open(&path).unwrap();
+        apply_key(&conn, "new").unwrap();
+        let x: String = conn.query_row("SELECT x FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(x, "kept");
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_random_key() {
+        let key = random_key().unwrap();
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(key, random_key().unwrap());
+    }
+
+    #[test]
+    fn test_is_age_file() {
+        assert!(is_age_file(b"age-encryption.org/v1\n-> X25519 abc\n"));
+        assert!(is_age_file(b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n"));
+        assert!(!is_age_file(b"[{\"id\":1}]"));
+    }
+
+    #[test]
+    fn test_missing_file_is_not_encrypted() {
+        let path = std::env::temp_dir().join("clipm-does-not-exist.db");
+        assert!(!is_encrypted(&path).unwrap());
+    }
+}
@@ -0,0 +1,360 @@
+//! File formats for `clipm export`: a JSON array of entries (lossless, images
+//! included as base64 PNG), CSV with one row per entry, or snippets for
+//! VS Code and Alfred. `clipm import` reads the JSON format back.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+
+use crate::i18n::t;
+use crate::models::{make_preview, ClipEntry, ClipImage, ClipmError, ContentType, Provenance, Usage};
+
+/// How `export` writes entries.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// JSON array with every field, images included
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// VS Code `.code-snippets` file; labels become prefixes, text entries only
+    VscodeSnippets,
+    /// Alfred `.alfredsnippets` collection; labels become keywords, labeled text entries only
+    AlfredSnippets,
+}
+
+impl ExportFormat {
+    /// Whether `entry` can be written in this format. Snippet formats hold
+    /// text only, and Alfred snippets need a label for their keyword.
+    pub fn includes(self, entry: &ClipEntry) -> bool {
+        match self {
+            ExportFormat::Json | ExportFormat::Csv => true,
+            ExportFormat::VscodeSnippets => entry.content_type.is_text(),
+            ExportFormat::AlfredSnippets => entry.content_type.is_text() && entry.label.is_some(),
+        }
+    }
+}
+
+/// One entry in an export file.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportEntry {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub content: String,
+    pub size: usize,
+    pub created_at: String,
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Base64 PNG of image entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Rich-text flavor of text entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tty: Option<String>,
+}
+
+impl From<&ClipEntry> for ExportEntry {
+    fn from(e: &ClipEntry) -> Self {
+        ExportEntry {
+            id: e.id,
+            content_type: e.content_type.to_string(),
+            content: e.content.clone(),
+            size: e.byte_size,
+            created_at: e.created_at.clone(),
+            label: e.label.clone(),
+            title: e.title.clone(),
+            width: e.image.as_ref().map(|i| i.width),
+            height: e.image.as_ref().map(|i| i.height),
+            image: e.image.as_ref().map(|i| BASE64.encode(&i.png)),
+            html: e.html.clone(),
+            cwd: e.provenance.cwd.clone(),
+            git_branch: e.provenance.git_branch.clone(),
+            tty: e.provenance.tty.clone(),
+        }
+    }
+}
+
+impl ExportEntry {
+    /// Validate and convert back to an entry. The ID is reset: imported
+    /// entries get new ones.
+    pub fn into_entry(self) -> Result<ClipEntry, ClipmError> {
+        let id = self.id;
+        let content_type: ContentType = self.content_type.parse().map_err(ClipmError::InvalidInput)?;
+        if chrono::DateTime::parse_from_rfc3339(&self.created_at).is_err() {
+            return Err(ClipmError::InvalidInput(t!("Entry #{id} has an invalid timestamp: {value}", id = id, value = self.created_at)));
+        }
+        let image = match (self.image, self.width, self.height) {
+            (None, _, _) => None,
+            (Some(data), Some(width), Some(height)) => {
+                let png = BASE64
+                    .decode(data)
+                    .map_err(|e| ClipmError::InvalidInput(t!("Entry #{id} has invalid image data: {error}", id = id, error = e)))?;
+                Some(ClipImage { width, height, png })
+            }
+            (Some(_), _, _) => return Err(ClipmError::InvalidInput(t!("Image entry #{id} is missing its width or height", id = id))),
+        };
+        Ok(ClipEntry {
+            id: 0,
+            byte_size: image.as_ref().map_or(self.content.len(), |i| i.png.len()),
+            preview: make_preview(&self.content, &content_type),
+            content: self.content,
+            content_type,
+            created_at: self.created_at,
+            label: self.label,
+            title: self.title,
+            image,
+            html: self.html,
+            provenance: Provenance { cwd: self.cwd, git_branch: self.git_branch, tty: self.tty, session_id: None },
+            usage: Usage::default(),
+        })
+    }
+}
+
+/// Parse a JSON export, oldest entries first.
+pub fn from_json(data: &[u8]) -> Result<Vec<ClipEntry>, ClipmError> {
+    let exported: Vec<ExportEntry> = serde_json::from_slice(data)
+        .map_err(|e| ClipmError::InvalidInput(t!("Not a clipm JSON export: {error}", error = e)))?;
+    let mut entries = exported.into_iter().map(ExportEntry::into_entry).collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    Ok(entries)
+}
+
+pub fn to_json(entries: &[ClipEntry]) -> Result<String, ClipmError> {
+    let entries: Vec<ExportEntry> = entries.iter().map(ExportEntry::from).collect();
+    serde_json::to_string_pretty(&entries).map_err(|e| ClipmError::Io(e.to_string()))
+}
+
+const CSV_HEADER: &str = "id,type,content,size,created_at,label,width,height,image,cwd,git_branch,tty";
+
+pub fn to_csv(entries: &[ClipEntry]) -> String {
+    let mut out = format!("{CSV_HEADER}\r\n");
+    for entry in entries {
+        let e = ExportEntry::from(entry);
+        let fields = [
+            e.id.to_string(),
+            e.content_type,
+            e.content,
+            e.size.to_string(),
+            e.created_at,
+            e.label.unwrap_or_default(),
+            e.width.map(|w| w.to_string()).unwrap_or_default(),
+            e.height.map(|h| h.to_string()).unwrap_or_default(),
+            e.image.unwrap_or_default(),
+            e.cwd.unwrap_or_default(),
+            e.git_branch.unwrap_or_default(),
+            e.tty.unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// One snippet in a `.code-snippets` file.
+#[derive(Serialize)]
+struct VscodeSnippet {
+    prefix: String,
+    body: Vec<String>,
+    description: String,
+}
+
+/// Text entries as VS Code snippets, triggered by their label (`clip-<id>`
+/// for unlabeled ones) and described by their title. `$` and `\` are
+/// escaped so content is inserted literally.
+pub fn to_vscode_snippets(entries: &[ClipEntry]) -> Result<String, ClipmError> {
+    let mut snippets = serde_json::Map::new();
+    for entry in entries.iter().filter(|e| ExportFormat::VscodeSnippets.includes(e)) {
+        let prefix = entry.label.clone().unwrap_or_else(|| format!("clip-{}", entry.id));
+        let name = if snippets.contains_key(&prefix) { format!("{prefix} #{}", entry.id) } else { prefix.clone() };
+        let body = entry
+            .content
+            .lines()
+            .map(|line| line.replace('\\', "\\\\").replace('$', "\\$"))
+            .collect();
+        let description = entry.title.clone().unwrap_or_else(|| format!("clipm #{}", entry.id));
+        let snippet = VscodeSnippet { prefix, body, description };
+        snippets.insert(name, serde_json::to_value(snippet).map_err(|e| ClipmError::Io(e.to_string()))?);
+    }
+    serde_json::to_string_pretty(&snippets).map_err(|e| ClipmError::Io(e.to_string()))
+}
+
+#[derive(Serialize)]
+struct AlfredFile {
+    alfredsnippet: AlfredSnippet,
+}
+
+/// One snippet in an Alfred collection.
+#[derive(Serialize)]
+struct AlfredSnippet {
+    snippet: String,
+    uid: String,
+    name: String,
+    keyword: String,
+}
+
+/// Labeled text entries as an Alfred snippet collection: a zip archive with
+/// one JSON file per snippet, keyed by the entry's label.
+pub fn to_alfred_snippets(entries: &[ClipEntry]) -> Result<Vec<u8>, ClipmError> {
+    let zip_err = |e: zip::result::ZipError| ClipmError::Io(e.to_string());
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for entry in entries.iter().filter(|e| ExportFormat::AlfredSnippets.includes(e)) {
+        let label = entry.label.clone().unwrap_or_default();
+        let uid = format!("clipm-{}", entry.id);
+        // Labels may be hierarchical (work/projA); '/' can't appear in a file name
+        zip.start_file(format!("{} [{uid}].json", label.replace('/', "-")), options).map_err(zip_err)?;
+        let file = AlfredFile {
+            alfredsnippet: AlfredSnippet { snippet: entry.content.clone(), uid, name: entry.title.clone().unwrap_or_else(|| label.clone()), keyword: label },
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(|e| ClipmError::Io(e.to_string()))?;
+        zip.write_all(&json)?;
+    }
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Quote a field per RFC 4180 when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ClipImage, ContentType};
+
+    fn entry(id: i64, content: &str, label: Option<&str>) -> ClipEntry {
+        ClipEntry {
+            id,
+            content: content.to_string(),
+            content_type: ContentType::Text,
+            byte_size: content.len(),
+            created_at: "2026-02-17T10:00:00Z".to_string(),
+            label: label.map(String::from),
+            title: None,
+            preview: String::new(),
+            image: None,
+            html: None,
+            provenance: Provenance::default(),
+            usage: Usage::default(),
+        }
+    }
+
+    #[test]
+    fn test_to_vscode_snippets() {
+        let mut password = entry(3, "hunter2", Some("pw"));
+        password.content_type = ContentType::Password;
+        let entries = [
+            entry(1, "echo $HOME\r\nls C:\\", Some("sh")),
+            entry(2, "again", Some("sh")),
+            password,
+            entry(4, "plain", None),
+        ];
+        let json: serde_json::Value = serde_json::from_str(&to_vscode_snippets(&entries).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "sh": { "prefix": "sh", "body": ["echo \\$HOME", "ls C:\\\\"], "description": "clipm #1" },
+                "sh #2": { "prefix": "sh", "body": ["again"], "description": "clipm #2" },
+                "clip-4": { "prefix": "clip-4", "body": ["plain"], "description": "clipm #4" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_alfred_snippets() {
+        let mut password = entry(2, "hunter2", Some("pw"));
+        password.content_type = ContentType::Password;
+        let entries = [entry(1, "Kind regards,\nAlice", Some("mail/sig")), password, entry(3, "unlabeled", None)];
+        let data = to_alfred_snippets(&entries).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).unwrap();
+        assert_eq!(archive.len(), 1);
+        let file = archive.by_name("mail-sig [clipm-1].json").unwrap();
+        let json: serde_json::Value = serde_json::from_reader(file).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "alfredsnippet": { "snippet": "Kind regards,\nAlice", "uid": "clipm-1", "name": "mail/sig", "keyword": "mail/sig" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_export_format_includes() {
+        let mut password = entry(1, "hunter2", Some("pw"));
+        password.content_type = ContentType::Password;
+        let unlabeled = entry(2, "text", None);
+        assert!(ExportFormat::Json.includes(&password));
+        assert!(!ExportFormat::VscodeSnippets.includes(&password));
+        assert!(ExportFormat::VscodeSnippets.includes(&unlabeled));
+        assert!(!ExportFormat::AlfredSnippets.includes(&unlabeled));
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut second = entry(2, "x", None);
+        second.provenance.git_branch = Some("main".to_string());
+        let csv = to_csv(&[entry(1, "hello, world", Some("work")), second]);
+        assert_eq!(
+            csv,
+            "id,type,content,size,created_at,label,width,height,image,cwd,git_branch,tty\r\n\
+             1,text,\"hello, world\",12,2026-02-17T10:00:00Z,work,,,,,,\r\n\
+             2,text,x,1,2026-02-17T10:00:00Z,,,,,,main,\r\n"
+        );
+    }
+
+    #[test]
+    fn test_from_json_validates() {
+        let entries = from_json(to_json(&[entry(5, "hello", Some("work"))]).unwrap().as_bytes()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, 0);
+        assert_eq!(entries[0].preview, "hello");
+        assert_eq!(entries[0].label.as_deref(), Some("work"));
+
+        assert!(from_json(b"not json").is_err());
+        let bad_type = r#"[{"id":1,"type":"video","content":"x","size":1,"created_at":"2026-02-17T10:00:00Z","label":null}]"#;
+        assert!(matches!(from_json(bad_type.as_bytes()), Err(ClipmError::InvalidInput(_))));
+        let bad_time = r#"[{"id":1,"type":"text","content":"x","size":1,"created_at":"yesterday","label":null}]"#;
+        assert!(matches!(from_json(bad_time.as_bytes()), Err(ClipmError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_json_round_trip_with_image() {
+        let mut image = entry(3, "", None);
+        image.content_type = ContentType::Image;
+        image.image = Some(ClipImage { width: 2, height: 1, png: vec![0x89, b'P', b'N', b'G'] });
+        let json = to_json(&[entry(1, "hello", Some("work")), image]).unwrap();
+        let parsed: Vec<ExportEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0].content, "hello");
+        assert_eq!(parsed[0].label.as_deref(), Some("work"));
+        assert!(parsed[0].image.is_none());
+        assert_eq!(parsed[1].content_type, "image");
+        assert_eq!((parsed[1].width, parsed[1].height), (Some(2), Some(1)));
+        assert_eq!(BASE64.decode(parsed[1].image.as_ref().unwrap()).unwrap(), b"\x89PNG");
+    }
+}
@@ -0,0 +1,104 @@
+//! fzf-style fuzzy matching for `search --fuzzy`: the query's characters
+//! must appear in order (ignoring case and accents), and matches score
+//! higher the more of them are adjacent or start a word.
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Points for every matched character.
+const MATCH: i64 = 16;
+/// Extra points when a character directly follows the previous match.
+const CONSECUTIVE: i64 = 12;
+/// Extra points for a match at the start of a word.
+const BOUNDARY: i64 = 8;
+/// Points lost per skipped character between two matches, up to `MAX_GAP`.
+const GAP_PENALTY: i64 = 1;
+const MAX_GAP: usize = 16;
+/// Only this many leading characters of long text are considered.
+const MAX_CHARS: usize = 2000;
+/// Starting positions tried for the first query character.
+const MAX_STARTS: usize = 32;
+
+/// How well `pattern` matches `text`, or `None` if its characters don't all
+/// occur in order. Whitespace in the pattern is ignored.
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).map(fold).collect();
+    let text: Vec<char> = text.chars().take(MAX_CHARS).collect();
+    let folded: Vec<char> = text.iter().copied().map(fold).collect();
+    let first = *pattern.first()?;
+    folded
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| c == first)
+        .take(MAX_STARTS)
+        // Once a start fails to match, every later one fails too
+        .map_while(|(start, _)| score_from(&pattern, &text, &folded, start))
+        .max()
+}
+
+/// Lowercase base letter: `É` and `é` both fold to `e` (NFD, then the
+/// combining marks dropped). One character in, one out, so positions in
+/// the folded text are positions in the original.
+fn fold(c: char) -> char {
+    let base = std::iter::once(c).nfd().find(|&c| !is_combining_mark(c)).unwrap_or(c);
+    base.to_lowercase().next().unwrap_or(base)
+}
+
+/// Greedy left-to-right match of `pattern` starting at `start`.
+fn score_from(pattern: &[char], text: &[char], folded: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut i = start;
+    for &p in pattern {
+        i += folded[i..].iter().position(|&c| c == p)?;
+        score += MATCH;
+        match previous {
+            Some(prev) if prev + 1 == i => score += CONSECUTIVE,
+            Some(prev) => score -= GAP_PENALTY * (i - prev - 1).min(MAX_GAP) as i64,
+            None => {}
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += BOUNDARY;
+        }
+        previous = Some(i);
+        i += 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_token_matches() {
+        assert!(score("clipb", "CLI clipboard manager").is_some());
+        assert!(score("clpbrd", "clipboard").is_some());
+        assert_eq!(score("xyz", "clipboard"), None);
+        assert_eq!(score("draobpilc", "clipboard"), None);
+        assert_eq!(score("", "clipboard"), None);
+        assert_eq!(score("clip", ""), None);
+    }
+
+    #[test]
+    fn test_adjacent_and_word_start_matches_rank_higher() {
+        let contiguous = score("clipb", "the clipboard").unwrap();
+        let scattered = score("clipb", "cold lips in public").unwrap();
+        assert!(contiguous > scattered);
+        let word_start = score("db", "open db now").unwrap();
+        let inside = score("db", "feedback").unwrap();
+        assert!(word_start > inside);
+    }
+
+    #[test]
+    fn test_best_start_wins() {
+        // The first "c" leads to a scattered match; the later one is contiguous
+        assert_eq!(score("cli", "c... clipm"), score("cli", "clipm"));
+    }
+
+    #[test]
+    fn test_case_and_whitespace_insensitive() {
+        assert_eq!(score("Clip Board", "clipboard"), score("clipboard", "CLIPBOARD"));
+        assert!(score("Ünï", "ünicode").is_some());
+    }
+}
@@ -0,0 +1,258 @@
+//! Values `clipm gen` and `clipm generate` make up: UUIDs, random tokens,
+//! passwords, and placeholder text and names for filling in forms.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::io::Read;
+
+use crate::i18n::t;
+use crate::models::{ClipmError, SecretString};
+
+/// Random bytes in a token when `hex` or `base64` is given without a count.
+const DEFAULT_BYTES: usize = 32;
+/// Keeps a typo in the count from putting megabytes on the clipboard.
+const MAX_BYTES: usize = 4096;
+/// The same for `lorem --paragraphs`.
+pub const MAX_PARAGRAPHS: usize = 100;
+
+/// What to generate, as named on the command line: `uuid`, `hex:N`,
+/// `base64:N`, `lorem`, `name` or `email`. Like `openssl rand`, N counts
+/// random bytes, not characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Generator {
+    /// A random (version 4) UUID
+    Uuid,
+    /// N random bytes, hex-encoded
+    Hex(usize),
+    /// N random bytes, base64-encoded
+    Base64(usize),
+    /// N paragraphs of lorem ipsum (`--paragraphs`, 1 when parsed)
+    Lorem(usize),
+    /// A made-up first and last name
+    Name,
+    /// A made-up address at a domain reserved for examples, so nothing is
+    /// ever delivered to it
+    Email,
+}
+
+impl std::str::FromStr for Generator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, count) = match s.split_once(':') {
+            Some((kind, count)) => (kind, Some(count)),
+            None => (s, None),
+        };
+        let bytes = || match count {
+            None => Ok(DEFAULT_BYTES),
+            Some(n) => n
+                .parse()
+                .ok()
+                .filter(|n| (1..=MAX_BYTES).contains(n))
+                .ok_or_else(|| t!("Invalid byte count in {value}: use 1 to {max}.", value = s, max = MAX_BYTES)),
+        };
+        match kind {
+            "uuid" if count.is_none() => Ok(Generator::Uuid),
+            "hex" => Ok(Generator::Hex(bytes()?)),
+            "base64" => Ok(Generator::Base64(bytes()?)),
+            "lorem" if count.is_none() => Ok(Generator::Lorem(1)),
+            "name" if count.is_none() => Ok(Generator::Name),
+            "email" if count.is_none() => Ok(Generator::Email),
+            _ => Err(t!("Unknown generator: {value}. Use uuid, hex:N, base64:N, lorem, name or email.", value = s)),
+        }
+    }
+}
+
+impl Generator {
+    pub fn generate(self) -> Result<String, ClipmError> {
+        Ok(match self {
+            Generator::Uuid => uuid(random_bytes(16)?),
+            Generator::Hex(n) => hex(&random_bytes(n)?),
+            Generator::Base64(n) => BASE64.encode(random_bytes(n)?),
+            Generator::Lorem(paragraphs) => lorem(paragraphs)?,
+            Generator::Name => format!("{} {}", pick(FIRST_NAMES)?, pick(LAST_NAMES)?),
+            Generator::Email => {
+                let (first, last) = (pick(FIRST_NAMES)?, pick(LAST_NAMES)?);
+                format!("{}.{}@{}", first.to_lowercase(), last.to_lowercase(), pick(EXAMPLE_DOMAINS)?)
+            }
+        })
+    }
+}
+
+const LOREM_OPENING: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit.";
+const LOREM_WORDS: &[&str] = &[
+    "a", "ac", "aliqua", "aliquam", "amet", "ante", "arcu", "at", "augue", "commodo", "consequat", "cras", "cursus", "dapibus", "diam",
+    "dictum", "dolor", "dolore", "donec", "duis", "egestas", "eget", "eiusmod", "elit", "enim", "erat", "eros", "est", "et", "etiam",
+    "eu", "ex", "facilisis", "fames", "felis", "fermentum", "gravida", "iaculis", "id", "in", "integer", "ipsum", "justo", "labore",
+    "lacus", "laoreet", "lectus", "libero", "ligula", "lorem", "magna", "massa", "mattis", "mauris", "metus", "mi", "minim", "morbi",
+    "nec", "neque", "nibh", "nisi", "non", "nulla", "nunc", "odio", "orci", "pellentesque", "pharetra", "porta", "posuere", "proin",
+    "purus", "quam", "quis", "risus", "rutrum", "sagittis", "sed", "sem", "semper", "sit", "tellus", "tempor", "tincidunt", "tortor",
+    "ullamco", "ut", "varius", "vel", "velit", "vitae", "viverra", "volutpat",
+];
+/// Words per sentence, and sentences per paragraph.
+const SENTENCE_WORDS: (usize, usize) = (6, 14);
+const PARAGRAPH_SENTENCES: (usize, usize) = (4, 7);
+
+const FIRST_NAMES: &[&str] = &[
+    "Aiko", "Amara", "Ana", "Carlos", "Chen", "Elena", "Fatima", "Hana", "Ivan", "James", "Kofi", "Lars", "Leila", "Lucas", "Maria",
+    "Mateo", "Noah", "Olga", "Priya", "Ravi", "Sofia", "Tomas", "Yusuf", "Zara",
+];
+const LAST_NAMES: &[&str] = &[
+    "Andersen", "Brown", "Costa", "Dimitrov", "Garcia", "Haddad", "Ivanova", "Kim", "Kowalski", "Mensah", "Muller", "Nakamura",
+    "Novak", "Okafor", "Patel", "Petrov", "Rossi", "Silva", "Smith", "Tanaka", "Yilmaz", "Zhang",
+];
+/// Reserved for documentation by RFC 2606.
+const EXAMPLE_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// `paragraphs` paragraphs of lorem ipsum, separated by blank lines. The
+/// first one opens with the familiar "Lorem ipsum dolor sit amet".
+fn lorem(paragraphs: usize) -> Result<String, ClipmError> {
+    let mut text = Vec::with_capacity(paragraphs);
+    for n in 0..paragraphs {
+        let mut sentences = Vec::new();
+        if n == 0 {
+            sentences.push(LOREM_OPENING.to_string());
+        }
+        for _ in 0..between(PARAGRAPH_SENTENCES)? {
+            let mut words = (0..between(SENTENCE_WORDS)?).map(|_| pick(LOREM_WORDS)).collect::<Result<Vec<_>, _>>()?.join(" ");
+            words[..1].make_ascii_uppercase();
+            sentences.push(format!("{words}."));
+        }
+        text.push(sentences.join(" "));
+    }
+    Ok(text.join("\n\n"))
+}
+
+/// A random item. Not for secrets: the modulo slightly favours early items.
+fn pick<'a>(items: &[&'a str]) -> Result<&'a str, ClipmError> {
+    Ok(items[random_index(items.len())?])
+}
+
+/// A random count in `min..=max`.
+fn between((min, max): (usize, usize)) -> Result<usize, ClipmError> {
+    Ok(min + random_index(max - min + 1)?)
+}
+
+fn random_index(len: usize) -> Result<usize, ClipmError> {
+    let bytes = random_bytes(4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize % len)
+}
+
+/// Characters `clipm generate` draws passwords from.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Charset {
+    /// Letters and digits
+    Alnum,
+    /// Letters, digits and punctuation
+    AlnumSymbols,
+    /// Digits only, e.g. for PINs
+    Digits,
+}
+
+const ALNUM: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+/// Only punctuation that means nothing to a shell, so the password survives
+/// being pasted into one (or a config file) unquoted.
+const ALNUM_SYMBOLS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_.,:@%+=^/";
+const DIGITS: &[u8] = b"0123456789";
+
+impl Charset {
+    fn chars(self) -> &'static [u8] {
+        match self {
+            Charset::Alnum => ALNUM,
+            Charset::AlnumSymbols => ALNUM_SYMBOLS,
+            Charset::Digits => DIGITS,
+        }
+    }
+}
+
+/// A password of `length` characters from `charset`, each equally likely:
+/// random bytes past the last whole multiple of the charset size are
+/// skipped rather than wrapped around, which would favour the first
+/// characters.
+pub fn password(length: usize, charset: Charset) -> Result<SecretString, ClipmError> {
+    let chars = charset.chars();
+    let limit = 256 - 256 % chars.len();
+    let mut password = SecretString::new(String::with_capacity(length));
+    while password.len() < length {
+        let bytes = zeroize::Zeroizing::new(random_bytes(length)?);
+        for &b in bytes.iter().filter(|&&b| usize::from(b) < limit) {
+            if password.len() == length {
+                break;
+            }
+            password.push(char::from(chars[usize::from(b) % chars.len()]));
+        }
+    }
+    Ok(password)
+}
+
+/// `n` bytes from the system's CSPRNG.
+pub fn random_bytes(n: usize) -> Result<Vec<u8>, ClipmError> {
+    let mut bytes = vec![0u8; n];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Format 16 random bytes as a version 4 UUID (RFC 9562): the version and
+/// variant bits are fixed, the other 122 stay random.
+fn uuid(mut bytes: Vec<u8>) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex(&bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generator() {
+        assert_eq!("uuid".parse(), Ok(Generator::Uuid));
+        assert_eq!("hex:16".parse(), Ok(Generator::Hex(16)));
+        assert_eq!("base64".parse(), Ok(Generator::Base64(DEFAULT_BYTES)));
+        assert!("hex:0".parse::<Generator>().is_err());
+        assert!("uuid:4".parse::<Generator>().is_err());
+        assert!("rot13".parse::<Generator>().is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        let uuid = Generator::Uuid.generate().unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+        assert_eq!(Generator::Hex(32).generate().unwrap().len(), 64);
+        assert_eq!(Generator::Base64(24).generate().unwrap().len(), 32);
+        assert_ne!(Generator::Hex(16).generate().unwrap(), Generator::Hex(16).generate().unwrap());
+    }
+
+    #[test]
+    fn test_fake_data() {
+        let text = Generator::Lorem(3).generate().unwrap();
+        assert!(text.starts_with(LOREM_OPENING));
+        assert_eq!(text.split("\n\n").count(), 3);
+        assert!(text.split("\n\n").all(|p| p.starts_with(char::is_uppercase) && p.ends_with('.')));
+        let name = Generator::Name.generate().unwrap();
+        assert_eq!(name.split(' ').count(), 2);
+        let email = Generator::Email.generate().unwrap();
+        let (user, domain) = email.split_once('@').unwrap();
+        assert!(user.contains('.') && EXAMPLE_DOMAINS.contains(&domain));
+        assert!("lorem:3".parse::<Generator>().is_err());
+    }
+
+    #[test]
+    fn test_password() {
+        let pin = password(6, Charset::Digits).unwrap();
+        assert_eq!(pin.len(), 6);
+        assert!(pin.bytes().all(|b| b.is_ascii_digit()));
+        let long = password(200, Charset::AlnumSymbols).unwrap();
+        assert_eq!(long.len(), 200);
+        assert!(long.bytes().all(|b| ALNUM_SYMBOLS.contains(&b)));
+        assert!(!ALNUM_SYMBOLS.iter().any(|b| b"$&;|()<>*?[]{}~#!'\"\\` ".contains(b)));
+        assert!(password(40, Charset::Alnum).unwrap().bytes().all(|b| b.is_ascii_alphanumeric()));
+    }
+}
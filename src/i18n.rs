@@ -0,0 +1,422 @@
+//! Message localization.
+//!
+//! Messages are written in English at the call site and double as catalog
+//! keys (gettext style): `t!("Deleted entry #{id}.", id = id)`. A locale that
+//! has no translation for a message falls back to the English text, so new
+//! messages only need a catalog entry once someone translates them.
+
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    En,
+    Bg,
+}
+
+impl Locale {
+    /// Parse a POSIX locale tag like `bg_BG.UTF-8` or a bare language like `bg`.
+    /// Unsupported languages fall back to English.
+    pub fn from_tag(tag: &str) -> Locale {
+        let language = tag.split(['_', '-', '.', '@']).next().unwrap_or("");
+        match language.to_ascii_lowercase().as_str() {
+            "bg" => Locale::Bg,
+            _ => Locale::En,
+        }
+    }
+}
+
+/// Translate then fill `{name}` placeholders.
+macro_rules! t {
+    ($msg:literal) => {
+        $crate::i18n::translate($msg).to_string()
+    };
+    ($msg:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::fill(
+            $crate::i18n::translate($msg),
+            &[$((stringify!($name), $value.to_string())),+],
+        )
+    };
+}
+pub(crate) use t;
+
+fn current() -> Locale {
+    static CURRENT: OnceLock<Locale> = OnceLock::new();
+    *CURRENT.get_or_init(detect)
+}
+
+/// `locale` in config wins, then the usual POSIX precedence.
+#[cfg(not(test))]
+fn detect() -> Locale {
+    let configured = crate::config::load().ok().and_then(|c| c.locale);
+    configured
+        .into_iter()
+        .chain(["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|v| std::env::var(v).ok()))
+        .find(|tag| !tag.is_empty())
+        .map(|tag| Locale::from_tag(&tag))
+        .unwrap_or(Locale::En)
+}
+
+/// Tests assert on English output regardless of the machine's locale.
+#[cfg(test)]
+fn detect() -> Locale {
+    Locale::En
+}
+
+pub fn translate(msg: &'static str) -> &'static str {
+    translate_to(current(), msg)
+}
+
+fn translate_to(locale: Locale, msg: &'static str) -> &'static str {
+    match locale {
+        Locale::En => msg,
+        Locale::Bg => bg(msg).unwrap_or(msg),
+    }
+}
+
+/// Replace `{name}` with the matching argument. Braces that don't name an
+/// argument are kept as-is.
+pub fn fill(template: &str, args: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| args.iter().find(|(name, _)| *name == &after[..end]).map(|(_, v)| (v, end)));
+        match value {
+            Some((v, end)) => {
+                out.push_str(v);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn bg(msg: &str) -> Option<&'static str> {
+    Some(match msg {
+        // commands
+        "Skipped: content matches most recent entry." => "Пропуснато: съдържанието съвпада с последния запис.",
+        "Skipped: same content was stored in the last {minutes} minutes." => "Пропуснато: същото съдържание е запазено през последните {minutes} минути.",
+        "Stored as entry #{id} ({size}, label: \"{label}\")." => "Запазено като запис #{id} ({size}, етикет: \"{label}\").",
+        "Stored as entry #{id} ({size})." => "Запазено като запис #{id} ({size}).",
+        "Captured: {preview}" => "Прихванато: {preview}",
+        "Label \"{label}\" is over its quota: removed {count} oldest entries." => "Етикетът \"{label}\" надхвърля квотата си: премахнати са {count} най-стари записа.",
+        "Label (Enter to skip): " => "Етикет (Enter за пропускане): ",
+        "Use existing label \"{suggestion}\" instead of \"{input}\"? [Y/n] " => "Да се използва ли съществуващият етикет \"{suggestion}\" вместо \"{input}\"? [Y/n] ",
+        "Entry #{id} labeled \"{label}\"." => "Запис #{id} получи етикет \"{label}\".",
+        "Label removed from entry #{id}." => "Етикетът на запис #{id} е премахнат.",
+        "A title can't be empty" => "Заглавието не може да е празно",
+        "Entry #{id} titled \"{title}\"." => "Запис #{id} получи заглавие \"{title}\".",
+        "Title removed from entry #{id}." => "Заглавието на запис #{id} е премахнато.",
+        "Copied entry #{id} to clipboard ({size})." => "Запис #{id} е копиран в клипборда ({size}).",
+        "Copied entry #{id} to clipboard ({size}) and removed it from history." => "Запис #{id} е копиран в клипборда ({size}) и премахнат от историята.",
+        "No entries in clipboard history." => "Няма записи в историята на клипборда.",
+        "No labeled entries." => "Няма записи с етикет.",
+        "No results for \"{query}\"." => "Няма резултати за \"{query}\".",
+        "No results at offset {offset} ({total} matches in total)." => "Няма резултати след отместване {offset} (общо съвпадения: {total}).",
+        "Showing {first}-{last} of {total} matches." => "Показани {first}-{last} от {total} съвпадения.",
+        "Delete entry #{id}?" => "Да се изтрие ли запис #{id}?",
+        "Delete {count} entries?" => "Да се изтрият ли {count} записа?",
+        "Delete all clipboard history?" => "Да се изтрие ли цялата история на клипборда?",
+        "Deleted entry #{id}." => "Запис #{id} е изтрит.",
+        "Cleared {count} entries." => "Изчистени записи: {count}.",
+        "Aborted." => "Отказано.",
+        "New passphrase: " => "Нова парола: ",
+        "Repeat passphrase: " => "Повторете паролата: ",
+        "Passphrase: " => "Парола: ",
+        "History is already encrypted." => "Историята вече е шифрована.",
+        "History is not encrypted." => "Историята не е шифрована.",
+        "Encrypted. The passphrase is saved in the Keychain until `clipm lock`." => "Шифровано. Паролата е запазена в Keychain до `clipm lock`.",
+        "Encrypted. Set CLIPM_PASSPHRASE to open the history (no Keychain available)." => "Шифровано. Задайте CLIPM_PASSPHRASE, за да отваряте историята (няма достъпен Keychain).",
+        "Unlocked: passphrase saved in the Keychain." => "Отключено: паролата е запазена в Keychain.",
+        "No password entries in history." => "Няма записи с пароли в историята.",
+        "Exported {count} entries to {path}." => "Изнесени са {count} записа в {path}.",
+        "Started session #{id} \"{name}\"." => "Започната е сесия #{id} \"{name}\".",
+        "No entries on {date}." => "Няма записи за {date}.",
+        "{date}: {count} entries" => "{date}: {count} записа",
+        "Pruned {count} entries outside the retention limits." => "Премахнати са {count} записа извън ограниченията за съхранение.",
+        "Entry #{id} is now {kind}." => "Запис #{id} е преобразуван в {kind}.",
+        "Entry #{id} is already {kind}." => "Запис #{id} вече е от тип {kind}.",
+        "All entries already have the detected type." => "Всички записи вече са с разпознатия тип.",
+        "Would reclassify {count} entries." => "Биха се прекласифицирали {count} записа.",
+        "Reclassified {count} entries." => "Прекласифицирани са {count} записа.",
+        "{count} entries" => "{count} записа",
+        "not installed" => "не е инсталиран",
+        "loaded" => "зареден",
+        "installed, not loaded" => "инсталиран, но не е зареден",
+        "nothing to remove" => "няма какво да се премахне",
+        "clipm top — {time} (Ctrl-C to quit)" => "clipm top — {time} (Ctrl-C за изход)",
+        "Captured:       {hour} in the last hour, {day} in the last 24h" => "Записани:       {hour} през последния час, {day} за последните 24 ч",
+        "Database:       {size} ({count} entries)" => "База данни:     {size} ({count} записа)",
+        "Snapshot agent: {status}" => "Агент за снимки: {status}",
+        "Prune agent:    {status}" => "Агент за почистване: {status}",
+        "Next password expiry: {countdown}" => "Следващо изтичане на парола: {countdown}",
+        "Oldest entry reaches max_age_days in: {countdown}" => "Най-старият запис достига max_age_days след: {countdown}",
+        "Latest:" => "Последни:",
+        "Invalid clipm URL \"{url}\": {reason}" => "Невалиден clipm URL \"{url}\": {reason}",
+        "must start with {scheme}" => "трябва да започва с {scheme}",
+        "unknown action \"{action}\" (expected get or store)" => "непознато действие \"{action}\" (очаква се get или store)",
+        "callback \"{callback}\" is not a scheme:// URL" => "обратното извикване \"{callback}\" не е URL от вида scheme://",
+        "callback \"{callback}\" can't be a file URL" => "обратното извикване \"{callback}\" не може да е file URL",
+        "Copy password entry #{id} to the clipboard? A clipm:// link asked for it." => "Да се копира ли паролата от запис #{id} в клипборда? Поиска я clipm:// връзка.",
+        "Entry #{id} is a password; copying it through a clipm:// URL was not confirmed." => "Запис #{id} е парола; копирането му чрез clipm:// URL не беше потвърдено.",
+        "Cancel" => "Отказ",
+        "Copy" => "Копиране",
+        "Invalid regular expression: {error}" => "Невалиден регулярен израз: {error}",
+        "Pinned entry #{id}." => "Запис #{id} е закачен.",
+        "Unpinned entry #{id}." => "Запис #{id} е откачен.",
+        "Cleared {count} entries (kept {kept} pinned)." => "Изчистени са {count} записа (запазени са {kept} закачени).",
+        "The trash is empty." => "Кошчето е празно.",
+        "Permanently delete {count} entries in the trash?" => "Окончателно изтриване на {count} записа в кошчето?",
+        "Permanently deleted {count} entries." => "Окончателно изтрити записи: {count}.",
+        "Restored entry #{id}." => "Запис #{id} е възстановен.",
+        "Restored {count} entries." => "Възстановени записи: {count}.",
+        "Nothing to undo." => "Няма какво да се отмени.",
+        "Skipped: content is only whitespace." => "Пропуснато: съдържанието е само интервали.",
+        "Skipped: content is shorter than {count} characters." => "Пропуснато: съдържанието е по-кратко от {count} знака.",
+        "Updated entry #{id} from the same burst ({size})." => "Запис #{id} е обновен от същата поредица ({size}).",
+        "Coalesced:      {count} stores merged into bursts" => "Обединени:      {count} запазвания, слети в поредици",
+        "Entry #{id} now expands from \"{trigger}\"." => "Запис #{id} вече се вмъква с \"{trigger}\".",
+        "Trigger removed from entry #{id}." => "Тригерът на запис #{id} е премахнат.",
+        "No triggers yet. Add one with `clipm trigger <ID> <TRIGGER>`." => "Все още няма тригери. Добавете с `clipm trigger <ID> <TRIGGER>`.",
+        "Trigger: {trigger}" => "Тригер: {trigger}",
+        "Uses: {count}" => "Използвания: {count}",
+        "Started session #{id}." => "Започната е сесия #{id}.",
+        "Ended session #{id}." => "Сесия #{id} е приключена.",
+        "No session is running." => "Няма активна сесия.",
+        "No sessions yet. Start one with `clipm session start [NAME]`." => "Все още няма сесии. Започнете с `clipm session start [ИМЕ]`.",
+        "running" => "активна",
+        "Would import {count} entries ({skipped} already in history)." => "Ще бъдат внесени {count} записа ({skipped} вече са в историята).",
+        "Imported {count} entries ({skipped} already in history)." => "Внесени са {count} записа ({skipped} вече са в историята).",
+        "shorter than {min} characters" => "по-къса от {min} знака",
+        "low entropy" => "ниска ентропия",
+        "seen in {count} breaches" => "срещната в {count} изтичания",
+        "All {count} password entries look strong." => "Всички {count} записа с пароли изглеждат силни.",
+        "{flagged} of {count} password entries need attention; `clipm delete` them once rotated." => "{flagged} от {count} записа с пароли изискват внимание; изтрийте ги с `clipm delete`, след като ги смените.",
+        "Locked: passphrase removed from the Keychain." => "Заключено: паролата е премахната от Keychain.",
+        "Encrypted. The key is sealed to your age identity in {path}." => "Шифровано. Ключът е запечатан с вашата age идентичност в {path}.",
+        "Rekeyed. The new key is sealed to your age identity in {path}." => "Ключът е сменен. Новият ключ е запечатан с вашата age идентичност в {path}.",
+        "Rekeyed. The new passphrase is saved in the Keychain." => "Ключът е сменен. Новата парола е запазена в Keychain.",
+        "Rekeyed. Set CLIPM_PASSPHRASE to the new passphrase." => "Ключът е сменен. Задайте новата парола в CLIPM_PASSPHRASE.",
+        "CLIPM_PASSPHRASE still holds the old passphrase; update it." => "CLIPM_PASSPHRASE все още съдържа старата парола; обновете я.",
+        "Data directory: {path}" => "Директория за данни: {path}",
+        "Database:       {path}" => "База данни:     {path}",
+        "Profile:        {name}" => "Профил:         {name}",
+        "default" => "по подразбиране",
+        "Config:         {path} (created)" => "Конфигурация:   {path} (създадена)",
+        "Config:         {path} (already exists, left unchanged)" => "Конфигурация:   {path} (вече съществува, не е променена)",
+        "Launch agent:   {path}" => "Launch агент:   {path}",
+        "Load it with:   launchctl load {path}" => "Заредете го с: launchctl load {path}",
+        "Cannot determine home directory" => "Не може да се определи домашната директория",
+        "No retention rules configured (see [retention] in the config file)." => "Няма зададени правила за съхранение (вижте [retention] в конфигурационния файл).",
+        "Nothing to prune." => "Няма нищо за изчистване.",
+        "Would prune {count} entries ({size})." => "Биха били изчистени {count} записа ({size}).",
+        "Pruned {count} entries ({size})." => "Изчистени записи: {count} ({size}).",
+        "Press a number to copy, any other key to cancel: " => "Натиснете цифра за копиране или друг клавиш за отказ: ",
+        "just now" => "току-що",
+        "{n}m ago" => "преди {n} мин",
+        "{n}h ago" => "преди {n} ч",
+        "{n}d ago" => "преди {n} д",
+        "ID: {id}" => "ID: {id}",
+        "Preview: {preview}" => "Преглед: {preview}",
+        "Label: {label}" => "Етикет: {label}",
+        "Created: {created}" => "Създаден: {created}",
+        // table headers
+        "ID" => "ID",
+        "Preview" => "Преглед",
+        "Label" => "Етикет",
+        "Created" => "Създаден",
+        "Uses" => "Използвания",
+        "Trigger" => "Тригер",
+        "Deleted" => "Изтрит",
+        "Length" => "Дължина",
+        "Entropy" => "Ентропия",
+        "Issues" => "Проблеми",
+        "Name" => "Име",
+        "Started" => "Начало",
+        "Ended" => "Край",
+        "Entries" => "Записи",
+        "Variables" => "Променливи",
+        "From" => "От",
+        "To" => "Към",
+        "Kept" => "Запазен",
+        "Removed" => "Премахнати",
+        // errors
+        "Error: {message}" => "Грешка: {message}",
+        "Clipboard error: {message}" => "Грешка в клипборда: {message}",
+        "Database error: {message}" => "Грешка в базата данни: {message}",
+        "I/O error: {message}" => "Входно-изходна грешка: {message}",
+        "Not found: {message}" => "Не е намерено: {message}",
+        "Invalid input: {message}" => "Невалидни данни: {message}",
+        "Config error: {message}" => "Грешка в конфигурацията: {message}",
+        "Clipboard is empty" => "Клипбордът е празен",
+        "History is locked: run `clipm unlock` or set CLIPM_PASSPHRASE" => "Историята е заключена: изпълнете `clipm unlock` или задайте CLIPM_PASSPHRASE",
+        "Wrong passphrase for the encrypted history" => "Грешна парола за шифрованата история",
+        "Cannot run `security` to access the Keychain: {error}" => "Не може да се изпълни `security` за достъп до Keychain: {error}",
+        "Could not save the passphrase in the Keychain" => "Паролата не можа да бъде запазена в Keychain",
+        "Could not remove the passphrase from the Keychain" => "Паролата не можа да бъде премахната от Keychain",
+        "Could not save the password in the Keychain" => "Паролата не можа да бъде запазена в Keychain",
+        "The password is missing from the Keychain (account {account})" => "Паролата липсва в Keychain (акаунт {account})",
+        "The team database {path} does not exist" => "Екипната база данни {path} не съществува",
+        "The team database {path} is encrypted; only unencrypted team databases are supported" => "Екипната база данни {path} е шифрована; поддържат се само нешифровани екипни бази данни",
+        "The team database {path} has schema version {found}, but this clipm needs {expected}; open it with the same clipm version" => "Екипната база данни {path} е със схема версия {found}, а тази версия на clipm изисква {expected}; отворете я със същата версия на clipm",
+        "No team database is configured (set `path` under [team] in config)" => "Няма настроена екипна база данни (задайте `path` в [team] в конфигурацията)",
+        "The team database is read-only; --delete only works on personal entries" => "Екипната база данни е само за четене; --delete работи само с лични записи",
+        "Copied team entry #{id} to clipboard ({size})." => "Екипен запис #{id} е копиран в клипборда ({size}).",
+        "--team can't be combined with a p: entry reference" => "--team не може да се комбинира с препратка p:",
+        "Unknown database prefix \"{prefix}:\" in {value}. Use p: for personal and t: for team entries." => "Непознат префикс на база данни \"{prefix}:\" в {value}. Използвайте p: за лични и t: за екипни записи.",
+        "The content was {size}, over max_entry_size: kept the first {limit}." => "Съдържанието беше {size}, над max_entry_size: запазени са първите {limit}.",
+        "Large copy ({size}), saved as a file" => "Голямо копие ({size}), запазено като файл",
+        "The content was {size}, over max_entry_size: saved it to {path} and stored the path." => "Съдържанието беше {size}, над max_entry_size: запазено е в {path} и е записан пътят.",
+        "Skipped: content is {size}, over max_entry_size ({limit})." => "Пропуснато: съдържанието е {size}, над max_entry_size ({limit}).",
+        "Invalid tokenizer: {value}" => "Невалиден токенизатор: {value}",
+        "Indexing" => "Индексиране",
+        "Importing" => "Импортиране",
+        "Exporting" => "Експортиране",
+        "Removing duplicates" => "Премахване на дубликати",
+        "Reindexed {count} entries with the \"{tokenizer}\" tokenizer." => "Преиндексирани са {count} записа с токенизатора \"{tokenizer}\".",
+        "Reindexed {count} entries." => "Преиндексирани са {count} записа.",
+        "No duplicate entries." => "Няма повтарящи се записи.",
+        "Would remove {count} duplicates ({size})." => "Ще бъдат премахнати {count} дубликата ({size}).",
+        "Removed {count} duplicates, reclaiming {size}." => "Премахнати са {count} дубликата, освободени са {size}.",
+        "Skipped: the same content is already in history." => "Пропуснато: същото съдържание вече е в историята.",
+        "Interrupted: {message}" => "Прекъснато: {message}",
+        "nothing was written" => "нищо не е записано",
+        "imported {done} of {count} entries; run the same command again to import the rest" => "внесени са {done} от {count} записа; изпълнете същата команда отново, за да внесете останалите",
+        "removed {done} of {count} duplicates; run `clipm dedupe` again to finish" => "премахнати са {done} от {count} дубликата; изпълнете `clipm dedupe` отново, за да завършите",
+        "Only text entries can be edited" => "Само текстови записи могат да се редактират",
+        "Entry #{id} was not changed." => "Запис #{id} не е променен.",
+        "The edited content is empty; entry #{id} was not changed" => "Редактираното съдържание е празно; запис #{id} не е променен",
+        "Updated entry #{id} ({size})." => "Запис #{id} е обновен ({size}).",
+        "Entry #{id} is not text; only text entries can be merged" => "Запис #{id} не е текст; само текстови записи могат да се обединяват",
+        "Merged {count} entries into entry #{id} ({size})." => "{count} записа са обединени в запис #{id} ({size}).",
+        "Backup: {backup} entries. History: {history} entries." => "Резервно копие: {backup} записа. История: {history} записа.",
+        "In both: {count}" => "И в двете: {count}",
+        "Added to history since the backup: {count}" => "Добавени в историята след копието: {count}",
+        "Only in the backup (deleted or edited since): {count}" => "Само в копието (изтрити или редактирани след това): {count}",
+        "The backup has every entry stored before it was made." => "Копието съдържа всички записи отпреди създаването му.",
+        "and {count} more" => "и още {count}",
+        "Missing from the backup: {count} ({ids})" => "Липсват в копието: {count} ({ids})",
+        "{path} is missing {count} entries that are in history" => "В {path} липсват {count} записа, които са в историята",
+        "Entry #{id} is not text; rules only apply to text" => "Запис #{id} не е текст; правилата важат само за текст",
+        "Pass --text or --id" => "Подайте --text или --id",
+        "[normalize] changes the text before anything else." => "[normalize] променя текста преди всичко останало.",
+        "No rule matches." => "Нито едно правило не съвпада.",
+        "label \"{label}\"" => "етикет \"{label}\"",
+        "type {kind}" => "тип {kind}",
+        "nothing to set" => "нищо за задаване",
+        "Rule {number} ('{pattern}') applies: {actions}." => "Правило {number} ('{pattern}') се прилага: {actions}.",
+        "Rule {number} ('{pattern}') also matches, but only the first match applies." => "Правило {number} ('{pattern}') също съвпада, но се прилага само първото съвпадение.",
+        "The content is {size}, over max_entry_size ({limit}): `oversize` decides what is stored." => "Съдържанието е {size}, над max_entry_size ({limit}): `oversize` решава какво се запазва.",
+        "Would store {kind} ({size}, label: \"{label}\"): {preview}" => "Ще бъде запазен {kind} ({size}, етикет: \"{label}\"): {preview}",
+        "Would store {kind} ({size}): {preview}" => "Ще бъде запазен {kind} ({size}): {preview}",
+        "Invalid byte count in {value}: use 1 to {max}." => "Невалиден брой байтове в {value}: използвайте от 1 до {max}.",
+        "Unknown generator: {value}. Use uuid, hex:N, base64:N, lorem, name or email." => "Непознат генератор: {value}. Използвайте uuid, hex:N, base64:N, lorem, name или email.",
+        "--paragraphs only applies to lorem" => "--paragraphs важи само за lorem",
+        "Copied {value}." => "Копирано: {value}.",
+        "Copied {value} and stored it as entry #{id}." => "Копирано: {value}, запазено като запис #{id}.",
+        "Stored a {length}-character password as entry #{id} and copied it." => "Парола от {length} знака е запазена като запис #{id} и копирана.",
+        "The clipboard will be cleared in {seconds} seconds." => "Клипбордът ще бъде изчистен след {seconds} секунди.",
+        "A snippet named \"{name}\" already exists" => "Вече има фрагмент с име \"{name}\"",
+        "No snippet named \"{name}\"" => "Няма фрагмент с име \"{name}\"",
+        "Missing values for {names}; pass them with --var NAME=VALUE" => "Липсват стойности за {names}; подайте ги с --var ИМЕ=СТОЙНОСТ",
+        "Invalid variable: {value}. Use NAME=VALUE." => "Невалидна променлива: {value}. Използвайте ИМЕ=СТОЙНОСТ.",
+        "Saved snippet \"{name}\"." => "Фрагментът \"{name}\" е запазен.",
+        "Saved snippet \"{name}\" with variables: {variables}." => "Фрагментът \"{name}\" е запазен с променливи: {variables}.",
+        "No snippets found." => "Няма намерени фрагменти.",
+        "Copied snippet \"{name}\" to clipboard ({size})." => "Фрагментът \"{name}\" е копиран в клипборда ({size}).",
+        "Deleted snippet \"{name}\"." => "Фрагментът \"{name}\" е изтрит.",
+        "Passphrase must not be empty" => "Паролата не може да е празна",
+        "Passphrases do not match" => "Паролите не съвпадат",
+        "Entry #{id} has an invalid timestamp: {value}" => "Запис #{id} има невалидно време: {value}",
+        "Entry #{id} has invalid image data: {error}" => "Запис #{id} има невалидни данни за изображение: {error}",
+        "Image entry #{id} is missing its width or height" => "На изображение #{id} липсва ширина или височина",
+        "Not a clipm JSON export: {error}" => "Не е JSON експорт на clipm: {error}",
+        "No session named \"{name}\"" => "Няма сесия с име \"{name}\"",
+        "Only text and password entries can be converted" => "Само текстови записи и пароли могат да се преобразуват",
+        "Invalid date: {date}" => "Невалидна дата: {date}",
+        "Entry #{id} is a password; add --reveal to print it." => "Запис #{id} е парола; добавете --reveal, за да го отпечатате.",
+        "Entry #{id} has no HTML flavor" => "Запис #{id} няма HTML вариант",
+        "The HTML flavor doesn't follow changes to the text; leave out --flavor html" => "HTML вариантът не следва промените в текста; пропуснете --flavor html",
+        "No session is running (start one with `clipm session start`)" => "Няма активна сесия (започнете с `clipm session start`)",
+        "Nothing to store: standard input is empty" => "Няма какво да се запази: стандартният вход е празен",
+        "Standard input is not UTF-8 text (use --type image for PNG data)" => "Стандартният вход не е UTF-8 текст (използвайте --type image за PNG данни)",
+        "Not a PNG image: {error}" => "Не е PNG изображение: {error}",
+        "Unsupported PNG color type" => "Неподдържан цветови тип на PNG",
+        "{path} is encrypted: pass --identity with the age identity that can decrypt it" => "{path} е шифрован: подайте --identity с age идентичността, която може да го дешифрира",
+        "Cannot run `{program}`: {error}" => "Не може да се изпълни `{program}`: {error}",
+        "`{program}` failed: {error}" => "`{program}` завърши с грешка: {error}",
+        "The key file {path} is damaged" => "Файлът с ключа {path} е повреден",
+        "Set `identity` in [encryption] to use a key file" => "Задайте `identity` в [encryption], за да използвате файл с ключ",
+        "Cannot run `curl` for the breach check: {error}" => "Не може да се изпълни `curl` за проверката за изтичания: {error}",
+        "Breach check failed: {error}" => "Проверката за изтичания е неуспешна: {error}",
+        "Stored image is not 8-bit RGBA" => "Запазеното изображение не е 8-битово RGBA",
+        "Copy did not stick: the clipboard was changed by another application" => "Копирането не се задържа: клипбордът е променен от друго приложение",
+        "No entry with id {id}" => "Няма запис с id {id}",
+        "No entry with id {id} in the trash" => "Няма запис с id {id} в кошчето",
+        "No entry with trigger \"{trigger}\"" => "Няма запис с тригер \"{trigger}\"",
+        "Trigger \"{trigger}\" is already used by entry #{id}" => "Тригерът \"{trigger}\" вече се използва от запис #{id}",
+        "A trigger can't be empty or contain whitespace" => "Тригерът не може да е празен или да съдържа интервали",
+        "No entries in history" => "Няма записи в историята",
+        "No entry at @{n}" => "Няма запис на позиция @{n}",
+        "Invalid entry reference: {value}. Use an ID like 42 or @N for the Nth most recent entry." => "Невалидна препратка към запис: {value}. Използвайте id като 42 или @N за N-тия най-нов запис.",
+        "Empty search query" => "Празна заявка за търсене",
+        "Invalid content type: {value}. Must be 'text', 'password', 'image', 'url', 'json' or 'code'." => "Невалиден тип съдържание: {value}. Трябва да е 'text', 'password', 'image', 'url', 'json' или 'code'.",
+        "Invalid date format: {format}" => "Невалиден формат на дата: {format}",
+        "Counter placeholder needs a name, e.g. {{counter:invoice}}" => "Броячът изисква име, напр. {{counter:invoice}}",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("bg_BG.UTF-8"), Locale::Bg);
+        assert_eq!(Locale::from_tag("bg"), Locale::Bg);
+        assert_eq!(Locale::from_tag("BG-bg"), Locale::Bg);
+        assert_eq!(Locale::from_tag("en_US.UTF-8"), Locale::En);
+        assert_eq!(Locale::from_tag("C"), Locale::En);
+        assert_eq!(Locale::from_tag("fr_FR"), Locale::En);
+    }
+
+    #[test]
+    fn test_translate_falls_back_to_english() {
+        assert_eq!(translate_to(Locale::Bg, "Aborted."), "Отказано.");
+        assert_eq!(translate_to(Locale::Bg, "Not in the catalog"), "Not in the catalog");
+        assert_eq!(translate_to(Locale::En, "Aborted."), "Aborted.");
+    }
+
+    #[test]
+    fn test_fill_named_args() {
+        let out = fill("Stored as entry #{id} ({size}).", &[("id", "4".into()), ("size", "5 B".into())]);
+        assert_eq!(out, "Stored as entry #4 (5 B).");
+    }
+
+    #[test]
+    fn test_fill_keeps_unknown_braces() {
+        let out = fill("{{counter:invoice}} {x} {", &[("id", "1".into())]);
+        assert_eq!(out, "{{counter:invoice}} {x} {");
+    }
+
+    #[test]
+    fn test_fill_does_not_rescan_values() {
+        let out = fill("{a} {b}", &[("a", "{b}".into()), ("b", "2".into())]);
+        assert_eq!(out, "{b} 2");
+    }
+
+    #[test]
+    fn test_t_macro() {
+        assert_eq!(t!("Deleted entry #{id}.", id = 7), "Deleted entry #7.");
+        assert_eq!(t!("Aborted."), "Aborted.");
+    }
+}
@@ -0,0 +1,40 @@
+//! Ctrl-C handling for commands that write in batches (`import`, `dedupe`)
+//! or to a file (`export`).
+//!
+//! After [`catch`], Ctrl-C only sets a flag. The command polls it with
+//! [`check`] between entries: the error rolls back the transaction in
+//! flight, and batches committed before it stay. A second Ctrl-C exits
+//! straight away.
+
+use crate::models::ClipmError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static HANDLER: Once = Once::new();
+
+/// Exit status after a second Ctrl-C (128 + SIGINT), as shells report it.
+const SIGINT_STATUS: i32 = 130;
+
+/// Install the handler. Until then Ctrl-C kills the process as usual, which
+/// is fine while nothing has been written.
+pub fn catch() {
+    HANDLER.call_once(|| {
+        // Without a handler Ctrl-C still kills the process mid-transaction,
+        // which SQLite rolls back on the next open
+        let _ = ctrlc::set_handler(|| {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(SIGINT_STATUS);
+            }
+        });
+    });
+}
+
+/// Fails with an empty `ClipmError::Interrupted` once Ctrl-C was pressed;
+/// the command replaces it with a message saying what was kept.
+pub fn check() -> Result<(), ClipmError> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(ClipmError::Interrupted(String::new()));
+    }
+    Ok(())
+}
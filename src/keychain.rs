@@ -0,0 +1,98 @@
+//! Generic passwords in the macOS login Keychain, through the `security`
+//! command-line tool. It holds the database passphrase (see `encryption`)
+//! and, with `[encryption] keychain_passwords`, the content of password
+//! entries. Off macOS every lookup finds nothing. Unit tests get an
+//! in-memory stand-in instead, so they never touch the real Keychain.
+
+#[cfg(not(test))]
+use std::io::Write;
+#[cfg(not(test))]
+use std::process::{Command, Stdio};
+
+#[cfg(not(test))]
+use crate::i18n::t;
+#[cfg(not(test))]
+use crate::models::{ClipmError, SecretString};
+
+/// Service name of every item clipm keeps in the Keychain.
+pub const SERVICE: &str = "clipm";
+
+#[cfg(test)]
+pub use fake::{delete, get, set};
+
+/// The item stored under `account`, if any.
+#[cfg(not(test))]
+pub fn get(account: &str) -> Option<SecretString> {
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let mut secret = SecretString::new(String::from_utf8(output.stdout).ok()?);
+    if secret.ends_with('\n') {
+        secret.pop();
+    }
+    (output.status.success() && !secret.is_empty()).then_some(secret)
+}
+
+/// Store `secret` under `account`, replacing any previous item. It goes
+/// through `security -i` on stdin so it never shows up in `ps`. `Ok(false)`
+/// if `security` refused.
+#[cfg(not(test))]
+pub fn set(account: &str, secret: &str) -> Result<bool, ClipmError> {
+    let quoted = SecretString::new(secret.replace('\\', "\\\\").replace('"', "\\\""));
+    let command = SecretString::new(format!("add-generic-password -U -s {SERVICE} -a {account} -w \"{}\"\n", *quoted));
+    let mut child = Command::new("security")
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ClipmError::Io(t!("Cannot run `security` to access the Keychain: {error}", error = e)))?;
+    let written = child.stdin.take().map(|mut stdin| stdin.write_all(command.as_bytes()));
+    let status = child.wait()?;
+    if let Some(Err(e)) = written {
+        return Err(e.into());
+    }
+    Ok(status.success())
+}
+
+/// Remove the item under `account`. `Ok(true)` also when there was none.
+#[cfg(not(test))]
+pub fn delete(account: &str) -> Result<bool, ClipmError> {
+    if get(account).is_none() {
+        return Ok(true);
+    }
+    let status = Command::new("security")
+        .args(["delete-generic-password", "-s", SERVICE, "-a", account])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+#[cfg(test)]
+mod fake {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use crate::models::{ClipmError, SecretString};
+
+    thread_local! {
+        static ITEMS: RefCell<HashMap<String, SecretString>> = RefCell::new(HashMap::new());
+    }
+
+    pub fn get(account: &str) -> Option<SecretString> {
+        ITEMS.with(|items| items.borrow().get(account).cloned())
+    }
+
+    pub fn set(account: &str, secret: &str) -> Result<bool, ClipmError> {
+        ITEMS.with(|items| items.borrow_mut().insert(account.to_string(), SecretString::new(secret.to_string())));
+        Ok(true)
+    }
+
+    pub fn delete(account: &str) -> Result<bool, ClipmError> {
+        ITEMS.with(|items| items.borrow_mut().remove(account));
+        Ok(true)
+    }
+}
@@ -0,0 +1,46 @@
+//! Storage for the `clipm` clipboard history.
+//!
+//! [`ClipStore`] is the entry point for other tools (TUIs, status bar widgets)
+//! that want to read or add entries. [`db`] and [`models`] expose the
+//! lower-level building blocks it is made of.
+//!
+//! ```
+//! let mut store = clipm::ClipStore::open_in_memory()?;
+//! store.add("ssh deploy@example.com", clipm::ContentType::Text, None)?;
+//! for entry in store.recent(5)? {
+//!     println!("{} {}", entry.id, entry.preview);
+//! }
+//! # Ok::<(), clipm::Error>(())
+//! ```
+
+pub mod audit;
+pub mod clipboard;
+/// Implementations of the `clipm` subcommands. They print to the terminal
+/// and are not meant for library use.
+#[doc(hidden)]
+pub mod commands;
+pub mod config;
+pub mod db;
+pub mod encryption;
+pub mod export;
+pub mod fuzzy;
+pub mod generate;
+pub mod i18n;
+pub mod interrupt;
+pub mod keychain;
+pub mod models;
+pub mod normalize;
+pub mod progress;
+pub mod rules;
+mod store;
+pub mod template;
+pub mod timeparse;
+pub mod url_scheme;
+
+pub use models::{ClipEntry, ClipSummary, ContentType, EntryRef};
+pub use store::ClipStore;
+
+/// Error returned by every fallible operation in this crate. Its variants
+/// are the error categories of the `clipm` command, and
+/// [`Error::exit_code`] gives the exit status the command reports for each.
+pub use models::ClipmError as Error;
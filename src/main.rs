@@ -1,30 +1,141 @@
 mod cli;
-mod clipboard;
-mod commands;
-mod db;
-mod models;
+mod completions;
 
 use clap::Parser;
-use cli::{Cli, Command};
+use cli::{Cli, Command, CompleteKind, RulesAction, SessionAction, SnippetAction, TrashAction};
+use clipm::clipboard::{Backend, ClipboardOverrides};
+use clipm::commands::{self, GetOutput, SearchMode};
+use clipm::config::{self, NormalizeConfig};
+use clipm::db::{ExportFilter, ListFilter, SearchFilter};
+use clipm::models::{ClipmError, EntryRef, Flavor, SourcedRef};
 
 fn main() {
-    let cli = Cli::parse();
+    // Usage errors exit with the "invalid input" code; clap's own 2 is "not found" here
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        let code = if e.use_stderr() { ClipmError::InvalidInput(String::new()).exit_code() } else { 0 };
+        std::process::exit(code);
+    });
+    let yes = cli.yes;
+    config::load_profile(cli.profile.as_deref());
+    let overrides = clipboard_overrides(&cli.command);
     let result = match cli.command {
-        Command::Store { label, content_type } => commands::store(label, &content_type),
-        Command::Get { id } => commands::get(id),
-        Command::List { limit, offset, label, days, content_type } => {
-            commands::list(limit, offset, label.as_deref(), days, content_type.as_deref())
+        Command::Init { launchd, encrypt } => commands::init(launchd, encrypt),
+        Command::Unlock => commands::unlock(),
+        Command::Profile => commands::profile(),
+        Command::Lock => commands::lock(),
+        Command::Rekey => commands::rekey(),
+        Command::Store { label, content_type, prompt_label, quiet, stdin, unique, .. } => {
+            commands::store(label, content_type, prompt_label, quiet, stdin, unique, overrides).map(|_| ())
         }
-        Command::Search { query, limit, days, content_type } => {
-            commands::search(&query, limit, days, content_type.as_deref())
+        Command::Get { id, team, expand_env, expand, clean_url, ascii, case, plain, flavor, delete, verify, format, stdout, reveal, paste_via_applescript, app, .. } => {
+            let flavor = if plain { Some(Flavor::Text) } else { flavor };
+            let output = match format {
+                Some(format) => GetOutput::Format(format),
+                None if stdout => GetOutput::Stdout { reveal, flavor },
+                None if paste_via_applescript => GetOutput::Paste { app, flavor },
+                None => GetOutput::Clipboard { verify, flavor, overrides },
+            };
+            let clean = NormalizeConfig { strip_tracking_params: clean_url, ascii_punctuation: ascii, nfc: ascii, case, ..Default::default() };
+            commands::get(id, team, expand_env, expand, clean, delete, output)
         }
-        Command::Label { id, label } => commands::label(id, label),
-        Command::Delete { id } => commands::delete(id),
-        Command::Clear { force } => commands::clear(force),
+        Command::Pop { id, stdout, reveal } => {
+            let output = if stdout { GetOutput::Stdout { reveal, flavor: None } } else { GetOutput::Clipboard { verify: false, flavor: None, overrides: ClipboardOverrides::default() } };
+            commands::get(id.map(SourcedRef::from), false, false, false, NormalizeConfig::default(), true, output)
+        }
+        Command::List { limit, offset, label, days, since, until, content_type, format, cwd, branch, session, pinned, sort, order, ids_only, null } => {
+            let ids = if null { Some('\0') } else if ids_only { Some('\n') } else { None };
+            let filter = ListFilter { label, days, since, until, content_type, cwd, branch, pinned, sort, order, ..Default::default() };
+            commands::list(limit, offset, filter, session.as_deref(), format, ids)
+        }
+        Command::Search { query, fuzzy, regex, limit, offset, label, days, since, until, content_type, format } => {
+            let mode = if fuzzy {
+                SearchMode::Fuzzy
+            } else if regex {
+                SearchMode::Regex
+            } else {
+                SearchMode::FullText
+            };
+            commands::search(&query, mode, limit, offset, SearchFilter { label, days, content_type, since, until }, format)
+        }
+        Command::Timeline { day } => commands::timeline(day),
+        Command::Top { interval, once } => commands::top(interval, once),
+        Command::Session { action } => match action {
+            SessionAction::Start { name } => commands::session_start(name),
+            SessionAction::End => commands::session_end(),
+            SessionAction::List => commands::session_list(),
+        },
+        Command::Snippet { action } => match action {
+            SnippetAction::Add { name, content, label } => commands::snippet_add(&name, content, label),
+            SnippetAction::List { query, label } => commands::snippet_list(query.as_deref(), label.as_deref()),
+            SnippetAction::Use { name, vars, stdout } => commands::snippet_use(&name, vars, stdout),
+            SnippetAction::Delete { name } => commands::snippet_delete(&name),
+        },
+        Command::Recent => commands::recent(),
+        Command::Tree => commands::tree(),
+        Command::Label { id, label, last } => match last {
+            Some(last) => commands::label(EntryRef::Recent(1), Some(last).filter(|l| !l.is_empty())),
+            None => commands::label(id.unwrap_or(EntryRef::Recent(1)), label),
+        },
+        Command::Title { id, title } => commands::title(id, title),
+        Command::Trigger { id, trigger } => commands::trigger(id, trigger),
+        Command::Triggers { format } => commands::triggers(format),
+        Command::Expand { trigger, stdout, reveal } => {
+            let output = if stdout { GetOutput::Stdout { reveal, flavor: None } } else { GetOutput::Clipboard { verify: false, flavor: None, overrides: ClipboardOverrides::default() } };
+            commands::expand(&trigger, output)
+        }
+        Command::Export { format, output, query, label, days, content_type, no_passwords, encrypt } => {
+            let filter = ExportFilter { query, label, days, content_type, exclude_passwords: no_passwords };
+            commands::export(format, output.as_deref(), &filter, encrypt.as_deref())
+        }
+        Command::Import { file, dry_run, identity } => commands::import(&file, dry_run, identity.as_deref()),
+        Command::Verify { file, identity, no_passwords } => commands::verify(&file, identity.as_deref(), no_passwords),
+        Command::Delete { ids } => commands::delete(&ids, yes),
+        Command::Trash { action: TrashAction::List } => commands::trash_list(),
+        Command::Trash { action: TrashAction::Empty } => commands::trash_empty(yes),
+        Command::Restore { ids } => commands::restore(&ids),
+        Command::Undo => commands::undo(),
+        Command::Edit { id } => commands::edit(id),
+        Command::Merge { ids, separator, delete } => commands::merge(&ids, &separator, delete),
+        Command::Convert { id, to } => commands::convert(id, to),
+        Command::Reclassify { dry_run } => commands::reclassify(dry_run),
+        Command::Dedupe { dry_run } => commands::dedupe(dry_run),
+        Command::Gen { generator, paragraphs, store, label } => commands::gen(generator, paragraphs.map(usize::from), store, label),
+        Command::Generate { length, charset, label, clear_after } => {
+            commands::generate_password(usize::from(length), charset, label, clear_after)
+        }
+        Command::Rules { action: RulesAction::Test { text, id } } => commands::rules_test(text, id),
+        Command::Reindex { tokenizer } => commands::reindex(tokenizer.as_deref()),
+        Command::Pin { ids } => commands::pin(&ids, true),
+        Command::Unpin { ids } => commands::pin(&ids, false),
+        Command::AuditPasswords { breaches } => commands::audit_passwords(breaches),
+        Command::Prune { dry_run, keep_days, keep_max } => commands::prune(dry_run, keep_days, keep_max),
+        Command::Clear { force } => commands::clear(force || yes),
+        Command::UrlHandler { url } => commands::url_handler(&url),
+        Command::Completions { shell } => {
+            print!("{}", completions::generate(shell));
+            Ok(())
+        }
+        Command::ClearClipboard { seconds } => commands::clear_clipboard_later(seconds),
+        Command::Complete { kind: CompleteKind::Labels } => commands::complete_labels(),
+        Command::Complete { kind: CompleteKind::Ids } => commands::complete_ids(),
     };
 
     if let Err(e) = result {
-        eprintln!("Error: {e}");
-        std::process::exit(1);
+        eprintln!("{}", commands::error_message(&e));
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// `--selection` and `get --osc52`, for the commands that take them.
+fn clipboard_overrides(command: &Command) -> ClipboardOverrides {
+    let mut overrides = ClipboardOverrides::default();
+    #[cfg(all(target_os = "linux", feature = "primary-selection"))]
+    if let Command::Store { selection, .. } | Command::Get { selection, .. } = command {
+        overrides.selection = *selection;
+    }
+    if let Command::Get { osc52: true, .. } = command {
+        overrides.backend = Some(Backend::Osc52);
     }
+    overrides
 }
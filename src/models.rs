@@ -1,9 +1,82 @@
+use serde::Deserialize;
 use std::fmt;
+use zeroize::Zeroize;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::i18n::t;
+
+/// A secret (password content, the database passphrase) that is zeroed when
+/// dropped, so it doesn't linger in freed heap memory.
+pub type SecretString = zeroize::Zeroizing<String>;
+
+/// Kind of content in an entry. Also the value set for `--type` on the CLI,
+/// so new variants are picked up by argument validation and completions.
+#[derive(Debug, Clone, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ContentType {
+    /// Plain text
     Text,
+    /// Secret: masked in listings and excluded from full-text search
     Password,
+    /// Picture, stored PNG-encoded; shown as `[image WxH, size]` in listings
+    Image,
+    /// Text that is a single URL
+    Url,
+    /// Text that parses as a JSON object or array
+    Json,
+    /// Multi-line text that looks like source code
+    Code,
+}
+
+/// Schemes a URL entry starts with.
+const URL_SCHEMES: &[&str] = &["http://", "https://", "ftp://", "file://", "ssh://"];
+
+/// Line openings that mark a line as source code.
+const CODE_LINE_STARTS: &[&str] = &[
+    "fn ", "pub ", "let ", "const ", "use ", "impl ", "def ", "class ", "import ", "from ", "function ", "return ",
+    "if (", "for (", "while (", "#include", "#!/", "package ", "func ", "var ", "} else",
+];
+
+impl ContentType {
+    /// URL, JSON and code entries are shown, searched and copied like plain
+    /// text; only passwords and images are special.
+    pub fn is_text(&self) -> bool {
+        !matches!(self, ContentType::Password | ContentType::Image)
+    }
+
+    /// The type `store` gives text when no `--type` is passed.
+    pub fn detect(content: &str) -> ContentType {
+        let trimmed = content.trim();
+        if is_url(trimmed) {
+            ContentType::Url
+        } else if is_json(trimmed) {
+            ContentType::Json
+        } else if looks_like_code(trimmed) {
+            ContentType::Code
+        } else {
+            ContentType::Text
+        }
+    }
+}
+
+fn is_url(s: &str) -> bool {
+    URL_SCHEMES.iter().any(|scheme| s.len() > scheme.len() && s.get(..scheme.len()).is_some_and(|p| p.eq_ignore_ascii_case(scheme)))
+        && !s.contains(char::is_whitespace)
+}
+
+fn is_json(s: &str) -> bool {
+    let bracketed = (s.starts_with('{') && s.ends_with('}')) || (s.starts_with('[') && s.ends_with(']'));
+    bracketed && serde_json::from_str::<serde_json::Value>(s).is_ok()
+}
+
+/// At least two non-blank lines, half of which end in `;`, `{` or `}` or
+/// start with a keyword.
+fn looks_like_code(s: &str) -> bool {
+    let lines: Vec<&str> = s.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let code_lines = lines
+        .iter()
+        .filter(|l| l.ends_with([';', '{', '}']) || CODE_LINE_STARTS.iter().any(|k| l.starts_with(*k)))
+        .count();
+    lines.len() >= 2 && code_lines * 2 >= lines.len()
 }
 
 impl fmt::Display for ContentType {
@@ -11,6 +84,10 @@ impl fmt::Display for ContentType {
         match self {
             ContentType::Text => write!(f, "text"),
             ContentType::Password => write!(f, "password"),
+            ContentType::Image => write!(f, "image"),
+            ContentType::Url => write!(f, "url"),
+            ContentType::Json => write!(f, "json"),
+            ContentType::Code => write!(f, "code"),
         }
     }
 }
@@ -22,11 +99,195 @@ impl std::str::FromStr for ContentType {
         match s {
             "text" => Ok(ContentType::Text),
             "password" => Ok(ContentType::Password),
-            _ => Err(format!("Invalid content type: {s}. Must be 'text' or 'password'.")),
+            "image" => Ok(ContentType::Image),
+            "url" => Ok(ContentType::Url),
+            "json" => Ok(ContentType::Json),
+            "code" => Ok(ContentType::Code),
+            _ => Err(t!("Invalid content type: {value}. Must be 'text', 'password', 'image', 'url', 'json' or 'code'.", value = s)),
+        }
+    }
+}
+
+/// How `list`, `search` and `get --format` print entries.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Box-drawn table
+    Table,
+    /// One "Field: value" line per field, blank line between entries
+    Plain,
+    /// A JSON array of entry objects, for scripts and `jq`
+    Json,
+}
+
+/// Which representation of a text entry `get --flavor` hands out.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Flavor {
+    /// Only the plain text
+    Text,
+    /// The HTML the entry was copied with
+    Html,
+}
+
+/// An entry as named on the command line: a plain ID (`42`) or a position
+/// counted back from the most recent entry (`@1` is the newest).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryRef {
+    Id(i64),
+    Recent(usize),
+}
+
+impl std::str::FromStr for EntryRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = match s.strip_prefix('@') {
+            Some(n) => n.parse().ok().filter(|&n| n > 0).map(EntryRef::Recent),
+            None => s.parse().ok().map(EntryRef::Id),
+        };
+        parsed.ok_or_else(|| t!("Invalid entry reference: {value}. Use an ID like 42 or @N for the Nth most recent entry.", value = s))
+    }
+}
+
+/// An entry reference that may name its database with a prefix: `t:17` or
+/// `t:@1` is in the team database, `p:42` in the personal history. Without a
+/// prefix (`source` is `None`) the caller decides, usually personal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourcedRef {
+    pub source: Option<Source>,
+    pub entry: EntryRef,
+}
+
+impl From<EntryRef> for SourcedRef {
+    fn from(entry: EntryRef) -> Self {
+        SourcedRef { source: None, entry }
+    }
+}
+
+impl std::str::FromStr for SourcedRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((prefix, rest)) = s.split_once(':') else {
+            return Ok(SourcedRef { source: None, entry: s.parse()? });
+        };
+        let source = match prefix {
+            "p" => Source::Personal,
+            "t" => Source::Team,
+            _ => return Err(t!("Unknown database prefix \"{prefix}:\" in {value}. Use p: for personal and t: for team entries.", prefix = prefix, value = s)),
+        };
+        Ok(SourcedRef { source: Some(source), entry: rest.parse()? })
+    }
+}
+
+/// Pixels of an image entry, PNG-encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipImage {
+    pub width: u32,
+    pub height: u32,
+    pub png: Vec<u8>,
+}
+
+/// Which database an entry was read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Source {
+    /// The history of the active profile
+    #[default]
+    Personal,
+    /// The read-only `[team]` database
+    Team,
+}
+
+impl Source {
+    /// The prefix of `SourcedRef`s naming this database, e.g. `t` in `t:17`.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Source::Personal => "p",
+            Source::Team => "t",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Personal => write!(f, "personal"),
+            Source::Team => write!(f, "team"),
+        }
+    }
+}
+
+/// Where an entry was stored from, recorded when `[store] capture_env` is on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    pub cwd: Option<String>,
+    pub git_branch: Option<String>,
+    /// Terminal device, or the terminal's session ID outside a tty
+    pub tty: Option<String>,
+    /// The `clipm session` running when the entry was stored
+    pub session_id: Option<i64>,
+}
+
+/// What `list` orders entries by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListSort {
+    /// When the entry was stored (newest first)
+    #[default]
+    Created,
+    /// How often `get` copied it (most first)
+    Used,
+    /// Content size (biggest first)
+    Size,
+    /// Label, A to Z; unlabeled entries last
+    Label,
+}
+
+impl ListSort {
+    /// The direction used when no `--order` is given.
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            ListSort::Label => SortOrder::Asc,
+            ListSort::Created | ListSort::Used | ListSort::Size => SortOrder::Desc,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// How often `get` has copied an entry, for `list --sort used`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub count: u32,
+    pub last_used_at: Option<String>,
+}
+
+/// A stretch of work started with `clipm session start`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: i64,
+    pub name: Option<String>,
+    pub started_at: String,
+    /// `None` while the session is running
+    pub ended_at: Option<String>,
+}
+
+/// A named template kept next to the history with `clipm snippet add`. Its
+/// content may hold `{{placeholders}}` that `snippet use` fills in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClipEntry {
     pub id: i64,
@@ -35,27 +296,138 @@ pub struct ClipEntry {
     pub byte_size: usize,
     pub created_at: String,
     pub label: Option<String>,
+    /// Human name shown instead of the preview (`clipm title`)
+    pub title: Option<String>,
+    /// Cached single-line preview (see `make_preview`); written by `db::insert`
+    pub preview: String,
+    /// Set for `ContentType::Image` entries, whose `content` is empty
+    pub image: Option<ClipImage>,
+    /// Rich-text (HTML) flavor copied along with `content`, if the source
+    /// app offered one; `get` puts both back on the clipboard
+    pub html: Option<String>,
+    pub provenance: Provenance,
+    pub usage: Usage,
+}
+
+/// Like `SecretString`, password content is zeroed when the entry is dropped.
+/// Entries are cloned rather than taken apart for the same reason.
+impl Drop for ClipEntry {
+    fn drop(&mut self) {
+        if self.content_type == ContentType::Password {
+            self.content.zeroize();
+        }
+    }
+}
+
+impl ClipEntry {
+    pub fn summary(&self) -> ClipSummary {
+        ClipSummary {
+            id: self.id,
+            preview: self.preview.clone(),
+            content_type: self.content_type.clone(),
+            byte_size: self.byte_size,
+            created_at: self.created_at.clone(),
+            label: self.label.clone(),
+            title: self.title.clone(),
+            image_size: self.image.as_ref().map(|i| (i.width, i.height)),
+            usage: self.usage.clone(),
+            source: Source::Personal,
+        }
+    }
+}
+
+/// Entry metadata without the content, as returned by `list`/`search`, so
+/// rendering a table never loads large content values.
+#[derive(Debug, Clone)]
+pub struct ClipSummary {
+    pub id: i64,
+    pub preview: String,
+    pub content_type: ContentType,
+    pub byte_size: usize,
+    pub created_at: String,
+    pub label: Option<String>,
+    pub title: Option<String>,
+    /// Width and height of image entries
+    pub image_size: Option<(u32, u32)>,
+    pub usage: Usage,
+    /// Always `Personal` from `db`; callers mark what they read from the team database
+    pub source: Source,
+}
+
+/// Characters kept in the cached preview column.
+pub const PREVIEW_CHARS: usize = 200;
+
+/// Single-line preview stored alongside the content so listings don't need to
+/// load it: the first `PREVIEW_CHARS` chars with each line break collapsed to a
+/// space. Passwords get an empty preview so the secret isn't duplicated, and
+/// images have no text to preview.
+pub fn make_preview(content: &str, content_type: &ContentType) -> String {
+    if matches!(content_type, ContentType::Password | ContentType::Image) {
+        return String::new();
+    }
+    content
+        .replace("\r\n", " ")
+        .chars()
+        .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
+        .take(PREVIEW_CHARS)
+        .collect()
 }
 
+/// Everything that can go wrong, for the `clipm` command and for library
+/// users (as `clipm::Error`). Messages are already translated and meant to
+/// be shown as they are; `Display` adds the category.
 #[derive(Debug)]
 pub enum ClipmError {
+    /// The clipboard couldn't be read or written
     Clipboard(String),
+    /// SQLite failed, or the history database is damaged
     Database(String),
+    /// A file, process or the Keychain failed
     Io(String),
+    /// No such entry, snippet, session or trash item
     NotFound(String),
+    /// Arguments or content that can't be used as given
     InvalidInput(String),
+    /// The config file is unreadable or wrong
+    Config(String),
+    /// There was nothing on the clipboard to store
     EmptyClipboard,
+    /// The database is encrypted and no passphrase is available
+    Locked,
+    /// Ctrl-C during a long command; says what was kept
+    Interrupted(String),
 }
 
 impl fmt::Display for ClipmError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ClipmError::Clipboard(msg) => write!(f, "Clipboard error: {msg}"),
-            ClipmError::Database(msg) => write!(f, "Database error: {msg}"),
-            ClipmError::Io(msg) => write!(f, "I/O error: {msg}"),
-            ClipmError::NotFound(msg) => write!(f, "Not found: {msg}"),
-            ClipmError::InvalidInput(msg) => write!(f, "Invalid input: {msg}"),
-            ClipmError::EmptyClipboard => write!(f, "Clipboard is empty"),
+            ClipmError::Clipboard(msg) => write!(f, "{}", t!("Clipboard error: {message}", message = msg)),
+            ClipmError::Database(msg) => write!(f, "{}", t!("Database error: {message}", message = msg)),
+            ClipmError::Io(msg) => write!(f, "{}", t!("I/O error: {message}", message = msg)),
+            ClipmError::NotFound(msg) => write!(f, "{}", t!("Not found: {message}", message = msg)),
+            ClipmError::InvalidInput(msg) => write!(f, "{}", t!("Invalid input: {message}", message = msg)),
+            ClipmError::Config(msg) => write!(f, "{}", t!("Config error: {message}", message = msg)),
+            ClipmError::EmptyClipboard => write!(f, "{}", t!("Clipboard is empty")),
+            ClipmError::Locked => write!(f, "{}", t!("History is locked: run `clipm unlock` or set CLIPM_PASSPHRASE")),
+            ClipmError::Interrupted(msg) => write!(f, "{}", t!("Interrupted: {message}", message = msg)),
+        }
+    }
+}
+
+impl ClipmError {
+    /// Process exit code for this error category, as the `clipm` command
+    /// exits with it (listed under "Exit codes" in the README). Tools built
+    /// on the library can use it to behave the same way.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ClipmError::Io(_) => 1,
+            ClipmError::NotFound(_) => 2,
+            ClipmError::Clipboard(_) | ClipmError::EmptyClipboard => 3,
+            ClipmError::Database(_) => 4,
+            ClipmError::InvalidInput(_) => 5,
+            ClipmError::Config(_) => 6,
+            ClipmError::Locked => 7,
+            ClipmError::Interrupted(_) => 130,
         }
     }
 }
@@ -70,7 +442,11 @@ impl From<rusqlite::Error> for ClipmError {
 
 impl From<arboard::Error> for ClipmError {
     fn from(e: arboard::Error) -> Self {
-        ClipmError::Clipboard(e.to_string())
+        match e {
+            // Nothing in the requested format, e.g. no text while an image is copied
+            arboard::Error::ContentNotAvailable => ClipmError::EmptyClipboard,
+            other => ClipmError::Clipboard(other.to_string()),
+        }
     }
 }
 
@@ -94,9 +470,49 @@ mod tests {
     fn test_content_type_from_str() {
         assert!(matches!("text".parse::<ContentType>(), Ok(ContentType::Text)));
         assert!(matches!("password".parse::<ContentType>(), Ok(ContentType::Password)));
+        assert!(matches!("image".parse::<ContentType>(), Ok(ContentType::Image)));
+        assert!(matches!("code".parse::<ContentType>(), Ok(ContentType::Code)));
         assert!("unknown".parse::<ContentType>().is_err());
     }
 
+    #[test]
+    fn test_content_type_detect() {
+        assert_eq!(ContentType::detect("https://example.com/a?b=c\n"), ContentType::Url);
+        assert_eq!(ContentType::detect("HTTP://EXAMPLE.COM"), ContentType::Url);
+        assert_eq!(ContentType::detect("see https://example.com"), ContentType::Text);
+        assert_eq!(ContentType::detect("https://"), ContentType::Text);
+        assert_eq!(ContentType::detect("{\"a\": [1, 2]}"), ContentType::Json);
+        assert_eq!(ContentType::detect("[1, 2"), ContentType::Text);
+        assert_eq!(ContentType::detect("{not json}"), ContentType::Text);
+        assert_eq!(ContentType::detect("fn main() {\n    println!(\"hi\");\n}"), ContentType::Code);
+        assert_eq!(ContentType::detect("import os\nprint(os.getcwd())"), ContentType::Code);
+        assert_eq!(ContentType::detect("let x = 1;"), ContentType::Text);
+        assert_eq!(ContentType::detect("Dear team,\nthe release is out.\nThanks"), ContentType::Text);
+        assert_eq!(ContentType::detect("日本語"), ContentType::Text);
+    }
+
+    #[test]
+    fn test_entry_ref_from_str() {
+        assert_eq!("42".parse::<EntryRef>(), Ok(EntryRef::Id(42)));
+        assert_eq!("@1".parse::<EntryRef>(), Ok(EntryRef::Recent(1)));
+        assert_eq!("@12".parse::<EntryRef>(), Ok(EntryRef::Recent(12)));
+        assert!("@0".parse::<EntryRef>().is_err());
+        assert!("@".parse::<EntryRef>().is_err());
+        assert!("@-1".parse::<EntryRef>().is_err());
+        assert!("abc".parse::<EntryRef>().is_err());
+    }
+
+    #[test]
+    fn test_sourced_ref_from_str() {
+        assert_eq!("42".parse::<SourcedRef>(), Ok(SourcedRef { source: None, entry: EntryRef::Id(42) }));
+        assert_eq!("p:42".parse::<SourcedRef>(), Ok(SourcedRef { source: Some(Source::Personal), entry: EntryRef::Id(42) }));
+        assert_eq!("t:17".parse::<SourcedRef>(), Ok(SourcedRef { source: Some(Source::Team), entry: EntryRef::Id(17) }));
+        assert_eq!("t:@1".parse::<SourcedRef>(), Ok(SourcedRef { source: Some(Source::Team), entry: EntryRef::Recent(1) }));
+        assert!("a:9".parse::<SourcedRef>().is_err());
+        assert!("t:".parse::<SourcedRef>().is_err());
+        assert!("t:abc".parse::<SourcedRef>().is_err());
+    }
+
     #[test]
     fn test_content_type_partial_eq() {
         assert_eq!(ContentType::Text, ContentType::Text);
@@ -104,6 +520,15 @@ mod tests {
         assert_ne!(ContentType::Text, ContentType::Password);
     }
 
+    #[test]
+    fn test_make_preview() {
+        assert_eq!(make_preview("line one\r\nline two\nthree", &ContentType::Text), "line one line two three");
+        assert_eq!(make_preview(&"x".repeat(500), &ContentType::Text).chars().count(), PREVIEW_CHARS);
+        assert_eq!(make_preview("日本語", &ContentType::Text), "日本語");
+        assert_eq!(make_preview("hunter2", &ContentType::Password), "");
+        assert_eq!(make_preview("", &ContentType::Image), "");
+    }
+
     #[test]
     fn test_error_display() {
         assert_eq!(ClipmError::EmptyClipboard.to_string(), "Clipboard is empty");
@@ -115,5 +540,22 @@ mod tests {
             ClipmError::InvalidInput("bad".into()).to_string(),
             "Invalid input: bad"
         );
+        assert_eq!(
+            ClipmError::Config("bad key".into()).to_string(),
+            "Config error: bad key"
+        );
+    }
+
+    #[test]
+    fn test_error_exit_codes() {
+        assert_eq!(ClipmError::Io("x".into()).exit_code(), 1);
+        assert_eq!(ClipmError::NotFound("x".into()).exit_code(), 2);
+        assert_eq!(ClipmError::Clipboard("x".into()).exit_code(), 3);
+        assert_eq!(ClipmError::EmptyClipboard.exit_code(), 3);
+        assert_eq!(ClipmError::Database("x".into()).exit_code(), 4);
+        assert_eq!(ClipmError::InvalidInput("x".into()).exit_code(), 5);
+        assert_eq!(ClipmError::Config("x".into()).exit_code(), 6);
+        assert_eq!(ClipmError::Locked.exit_code(), 7);
+        assert_eq!(ClipmError::Interrupted("x".into()).exit_code(), 130);
     }
 }
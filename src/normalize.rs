@@ -0,0 +1,261 @@
+//! Clean-ups applied to text by `clipm store`, each switched on in the
+//! `[normalize]` config section, and on demand by `get` (`--clean-url`,
+//! `--ascii`, `--case`).
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::NormalizeConfig;
+
+/// Invisible characters that sneak in from web pages and chat apps.
+const ZERO_WIDTH: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/// Query parameters that only identify where a link was shared from, grouped
+/// by source: Google, Meta, Microsoft, Yandex, X, TikTok, LinkedIn, then
+/// newsletter tools (Mailchimp, HubSpot, Marketo, Klaviyo, Kit, Vero).
+const TRACKING_PARAMS: &[&str] = &[
+    "gclid", "gclsrc", "dclid", "gbraid", "wbraid", "_ga", "_gl", "srsltid", "fbclid", "igshid", "igsh", "msclkid", "yclid", "twclid", "ttclid",
+    "li_fat_id", "mc_cid", "mc_eid", "_hsenc", "_hsmkt", "mkt_tok", "_kx", "ck_subscriber_id", "vero_id", "vero_conv",
+];
+
+/// Parameter name prefixes of whole tracking families: Google's `utm_*`,
+/// Matomo's `pk_*`/`mtm_*` and HubSpot ads' `hsa_*`.
+const TRACKING_PREFIXES: &[&str] = &["utm_", "pk_", "mtm_", "hsa_"];
+
+/// Identifier spellings for `get --case`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Case {
+    /// snake_case
+    Snake,
+    /// camelCase
+    Camel,
+    /// kebab-case
+    Kebab,
+    /// Title Case
+    Title,
+}
+
+/// `content` with the enabled clean-ups applied.
+pub fn apply(content: &str, rules: &NormalizeConfig) -> String {
+    let mut text = content.to_string();
+    if rules.crlf_to_lf {
+        text = text.replace("\r\n", "\n");
+    }
+    if rules.strip_zero_width {
+        text.retain(|c| !ZERO_WIDTH.contains(&c));
+    }
+    if rules.nfc {
+        text = text.nfc().collect();
+    }
+    if rules.ascii_punctuation {
+        text = ascii_punctuation(&text);
+    }
+    if rules.trim_trailing_whitespace {
+        text = trim_trailing_whitespace(&text);
+    }
+    if rules.strip_tracking_params {
+        if let Some(url) = strip_tracking_params(&text) {
+            text = url;
+        }
+    }
+    if let Some(case) = rules.case {
+        text = convert_case(&text, case);
+    }
+    text
+}
+
+/// Each line of `text` respelled in `case`. Anything but letters and digits
+/// separates words and is dropped; inside camelCase and PascalCase a word
+/// starts at an uppercase letter, keeping acronyms together (`HTTPServer`
+/// is `HTTP` + `Server`). Letters are told apart and cased by Unicode rules,
+/// so `straßeÜber` splits into `straße` and `Über`.
+fn convert_case(text: &str, case: Case) -> String {
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            let words = split_words(line);
+            match case {
+                Case::Snake => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+                Case::Kebab => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("-"),
+                Case::Camel => words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalize(w) })
+                    .collect(),
+                Case::Title => words.iter().map(|w| capitalize(w)).collect::<Vec<_>>().join(" "),
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn split_words(line: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(from) = start.take() {
+                words.push(&line[from..at]);
+            }
+            continue;
+        }
+        if let Some(from) = start {
+            let prev = chars[i - 1].1;
+            let next_lower = chars.get(i + 1).is_some_and(|&(_, n)| n.is_lowercase());
+            // fooBar and v2Beta, or the last capital of an acronym
+            if c.is_uppercase() && (!prev.is_uppercase() || next_lower) {
+                words.push(&line[from..at]);
+                start = Some(at);
+            }
+        } else {
+            start = Some(at);
+        }
+    }
+    if let Some(from) = start {
+        words.push(&line[from..]);
+    }
+    words
+}
+
+/// Uppercase first letter, the rest lowercase.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.as_str().to_lowercase().chars()).collect(),
+        None => String::new(),
+    }
+}
+
+/// Typographic punctuation, as inserted by chat apps, word processors and
+/// macOS smart quotes, replaced with what a shell or compiler expects. An em
+/// dash becomes `--` because that is usually what it was typed as.
+fn ascii_punctuation(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => ascii.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => ascii.push('"'),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => ascii.push('-'),
+            '\u{2014}' | '\u{2015}' => ascii.push_str("--"),
+            '\u{2026}' => ascii.push_str("..."),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => ascii.push(' '),
+            c => ascii.push(c),
+        }
+    }
+    ascii
+}
+
+/// Trailing spaces and tabs removed from every line, and trailing blank
+/// lines from the end.
+fn trim_trailing_whitespace(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').map(|line| line.trim_end_matches([' ', '\t', '\r'])).collect();
+    lines.join("\n").trim_end().to_string()
+}
+
+/// The URL without `utm_*` and other tracking parameters, if `text` is a
+/// single http(s) URL that has any.
+fn strip_tracking_params(text: &str) -> Option<String> {
+    let url = text.trim();
+    let is_http = url.starts_with("http://") || url.starts_with("https://");
+    if !is_http || url.contains(char::is_whitespace) {
+        return None;
+    }
+    let (rest, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    let (base, query) = rest.split_once('?')?;
+    let is_tracking = |pair: &&str| {
+        let key = pair.split('=').next().unwrap_or_default();
+        TRACKING_PREFIXES.iter().any(|prefix| key.starts_with(prefix)) || TRACKING_PARAMS.contains(&key)
+    };
+    let params: Vec<&str> = query.split('&').filter(|pair| !pair.is_empty()).collect();
+    let kept: Vec<&str> = params.iter().copied().filter(|pair| !is_tracking(pair)).collect();
+    if kept.len() == params.len() {
+        return None;
+    }
+    let mut cleaned = base.to_string();
+    if !kept.is_empty() {
+        cleaned.push('?');
+        cleaned.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        cleaned.push('#');
+        cleaned.push_str(fragment);
+    }
+    Some(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> NormalizeConfig {
+        NormalizeConfig {
+            trim_trailing_whitespace: true,
+            crlf_to_lf: true,
+            strip_zero_width: true,
+            strip_tracking_params: true,
+            ascii_punctuation: true,
+            nfc: true,
+            case: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_nothing_enabled() {
+        let text = "a  \r\nb\u{200B}\n\n";
+        assert_eq!(apply(text, &NormalizeConfig::default()), text);
+    }
+
+    #[test]
+    fn test_apply_each_rule() {
+        let only = |rules: NormalizeConfig, text: &str| apply(text, &rules);
+        let off = NormalizeConfig::default();
+        assert_eq!(only(NormalizeConfig { crlf_to_lf: true, ..off }, "a\r\nb\r"), "a\nb\r");
+        assert_eq!(only(NormalizeConfig { strip_zero_width: true, ..off }, "to\u{200B}ken\u{FEFF}"), "token");
+        assert_eq!(only(NormalizeConfig { trim_trailing_whitespace: true, ..off }, "  a \t\nb  \n\n"), "  a\nb");
+        assert_eq!(apply("x = 1;  \r\n\u{200D}y = 2;\r\n", &all()), "x = 1;\ny = 2;");
+    }
+
+    #[test]
+    fn test_ascii_punctuation() {
+        assert_eq!(ascii_punctuation("echo \u{201C}it\u{2019}s\u{201D} \u{2014}verbose \u{2013}n 1\u{2026}"), "echo \"it's\" --verbose -n 1...");
+        assert_eq!(ascii_punctuation("10\u{00A0}km \u{2212}3"), "10 km -3");
+        assert_eq!(ascii_punctuation("«café» ✓"), "«café» ✓");
+    }
+
+    #[test]
+    fn test_nfc() {
+        let decomposed = "cafe\u{0301}";
+        let rules = NormalizeConfig { nfc: true, ..Default::default() };
+        assert_eq!(apply(decomposed, &rules), "caf\u{00E9}");
+        assert_eq!(apply(decomposed, &NormalizeConfig::default()), decomposed);
+    }
+
+    #[test]
+    fn test_convert_case() {
+        assert_eq!(convert_case("parseHTTPResponse", Case::Snake), "parse_http_response");
+        assert_eq!(convert_case("user_id v2Beta", Case::Kebab), "user-id-v2-beta");
+        assert_eq!(convert_case("max-retry-count", Case::Camel), "maxRetryCount");
+        assert_eq!(convert_case("  the quick_brown-FOX ", Case::Title), "The Quick Brown Fox");
+        assert_eq!(convert_case("straßeÜber ÉCOLE", Case::Snake), "straße_über_école");
+        assert_eq!(convert_case("ÉtatCivil\nfirst name", Case::Camel), "étatCivil\nfirstName");
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        assert_eq!(
+            strip_tracking_params("https://example.com/a?id=3&utm_source=x&fbclid=abc#top").as_deref(),
+            Some("https://example.com/a?id=3#top")
+        );
+        assert_eq!(strip_tracking_params("https://example.com/?utm_medium=email").as_deref(), Some("https://example.com/"));
+        assert_eq!(strip_tracking_params("https://example.com/?id=3"), None);
+        assert_eq!(strip_tracking_params("see https://example.com/?utm_source=x"), None);
+        assert_eq!(strip_tracking_params("ftp://example.com/?utm_source=x"), None);
+        assert_eq!(
+            strip_tracking_params("https://shop.example/p?pk_campaign=x&size=m&mkt_tok=abc&hsa_ad=1&ttclid=2").as_deref(),
+            Some("https://shop.example/p?size=m")
+        );
+    }
+}
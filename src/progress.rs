@@ -0,0 +1,32 @@
+//! Progress bars on stderr for commands that can run for a while on large
+//! histories (`import`, `export`, `dedupe`, `reindex`).
+//!
+//! They are hidden when stderr isn't a terminal, so scripts and logs only
+//! see the final summary line.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+/// A bar counting up to `total`, e.g. "Importing [=====>    ] 1200/5000".
+/// `hidden` also suppresses it, e.g. while machine-readable output goes to
+/// the terminal.
+pub fn bar(message: String, total: u64, hidden: bool) -> ProgressBar {
+    if hidden || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} ({eta})")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+    ProgressBar::new(total).with_style(style).with_message(message)
+}
+
+/// A spinner for work with no known length, such as one big query.
+pub fn spinner(message: String, hidden: bool) -> ProgressBar {
+    if hidden || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
@@ -0,0 +1,43 @@
+//! `[[rules]]` from the config file, which label and type new text entries
+//! as `clipm store` (and so the snapshot agent) saves them.
+
+use crate::config::Rule;
+
+/// The first rule whose `match` regex is found in `content`.
+pub fn first_match<'a>(rules: &'a [Rule], content: &str) -> Option<&'a Rule> {
+    rules.iter().find(|rule| rule.pattern.is_match(content))
+}
+
+/// Every rule whose regex is found in `content`, with its index, in config
+/// order. Only the first applies; `clipm rules test` shows the rest.
+pub fn matching<'a>(rules: &'a [Rule], content: &'a str) -> impl Iterator<Item = (usize, &'a Rule)> + 'a {
+    rules.iter().enumerate().filter(move |(_, rule)| rule.pattern.is_match(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ContentType;
+
+    fn rule(pattern: &str, label: Option<&str>, content_type: Option<ContentType>) -> Rule {
+        Rule { pattern: regex::Regex::new(pattern).unwrap(), label: label.map(String::from), content_type }
+    }
+
+    #[test]
+    fn test_first_match() {
+        let rules = [
+            rule(r"\b[A-Z]+-\d+\b", Some("tickets"), None),
+            rule(r"^ghp_\w+$", None, Some(ContentType::Password)),
+            rule(r"\d+", Some("numbers"), None),
+        ];
+        assert_eq!(first_match(&rules, "see PROJ-123").and_then(|r| r.label.as_deref()), Some("tickets"));
+        assert_eq!(first_match(&rules, "ghp_abc123").and_then(|r| r.content_type.clone()), Some(ContentType::Password));
+        // Earlier rules win
+        assert_eq!(first_match(&rules, "PROJ-7 and 42").and_then(|r| r.label.as_deref()), Some("tickets"));
+        assert!(first_match(&rules, "nothing here").is_none());
+        assert!(first_match(&[], "PROJ-1").is_none());
+
+        let indexes: Vec<usize> = matching(&rules, "PROJ-7 and 42").map(|(i, _)| i).collect();
+        assert_eq!(indexes, vec![0, 2]);
+    }
+}
@@ -0,0 +1,166 @@
+use rusqlite::Connection;
+use std::path::Path;
+
+use crate::db;
+use crate::models::{make_preview, ClipEntry, ClipSummary, ClipmError, ContentType, Provenance, Usage};
+
+/// The clipboard history, for tools that want to read or add entries without
+/// going through the `clipm` command line.
+///
+/// ```
+/// use clipm::{ClipStore, ContentType};
+///
+/// let mut store = ClipStore::open_in_memory()?;
+/// let id = store.add("cargo test --workspace", ContentType::Text, Some("commands"))?;
+///
+/// assert_eq!(store.get(id)?.content, "cargo test --workspace");
+/// assert_eq!(store.latest()?.id, id);
+/// assert_eq!(store.search("cargo", 10)?[0].label.as_deref(), Some("commands"));
+/// # Ok::<(), clipm::Error>(())
+/// ```
+///
+/// Everything else in [`db`] is available through [`ClipStore::connection`].
+pub struct ClipStore {
+    conn: Connection,
+}
+
+impl ClipStore {
+    /// Open the history `clipm` itself uses, creating it on first use.
+    pub fn open() -> Result<ClipStore, ClipmError> {
+        Ok(ClipStore { conn: db::open()? })
+    }
+
+    /// Open (or create) a history database at `path`.
+    pub fn open_at(path: &Path) -> Result<ClipStore, ClipmError> {
+        Ok(ClipStore { conn: db::open_at(path)? })
+    }
+
+    /// A throwaway history that lives only as long as the returned value.
+    pub fn open_in_memory() -> Result<ClipStore, ClipmError> {
+        ClipStore::open_at(Path::new(":memory:"))
+    }
+
+    /// Store `content` as a new entry and return its ID. Unlike `clipm store`,
+    /// there is no duplicate check.
+    ///
+    /// ```
+    /// # use clipm::{ClipStore, ContentType};
+    /// let mut store = ClipStore::open_in_memory()?;
+    /// let id = store.add("hunter2", ContentType::Password, None)?;
+    /// // Password previews are never cached
+    /// assert_eq!(store.get(id)?.preview, "");
+    /// # Ok::<(), clipm::Error>(())
+    /// ```
+    pub fn add(&mut self, content: &str, content_type: ContentType, label: Option<&str>) -> Result<i64, ClipmError> {
+        let entry = ClipEntry {
+            id: 0,
+            content: content.to_string(),
+            byte_size: content.len(),
+            preview: make_preview(content, &content_type),
+            content_type,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            label: label.map(String::from),
+            title: None,
+            image: None,
+            html: None,
+            provenance: Provenance::default(),
+            usage: Usage::default(),
+        };
+        db::with_write_tx(&mut self.conn, |tx| db::insert(tx, &entry))
+    }
+
+    /// The full entry, content included. Fails with `NotFound` for unknown IDs.
+    pub fn get(&self, id: i64) -> Result<ClipEntry, ClipmError> {
+        db::get_by_id(&self.conn, id)
+    }
+
+    /// The most recently stored entry.
+    pub fn latest(&self) -> Result<ClipEntry, ClipmError> {
+        db::get_most_recent(&self.conn)
+    }
+
+    /// Up to `limit` entries, newest first, without their content.
+    ///
+    /// ```
+    /// # use clipm::{ClipStore, ContentType};
+    /// let mut store = ClipStore::open_in_memory()?;
+    /// store.add("first", ContentType::Text, None)?;
+    /// store.add("second", ContentType::Text, None)?;
+    /// let previews: Vec<_> = store.recent(10)?.into_iter().map(|e| e.preview).collect();
+    /// assert_eq!(previews, ["second", "first"]);
+    /// # Ok::<(), clipm::Error>(())
+    /// ```
+    pub fn recent(&self, limit: usize) -> Result<Vec<ClipSummary>, ClipmError> {
+        db::list(&self.conn, limit, 0, &db::ListFilter::default())
+    }
+
+    /// Full-text search over content and labels, best matches first.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<ClipSummary>, ClipmError> {
+        db::search(&self.conn, query, limit, 0, &db::SearchFilter::default())
+    }
+
+    /// Set or (with `None`) remove an entry's label.
+    pub fn set_label(&self, id: i64, label: Option<&str>) -> Result<(), ClipmError> {
+        db::update_label(&self.conn, id, label)
+    }
+
+    /// Set or (with `None`) remove an entry's title, its human name.
+    pub fn set_title(&self, id: i64, title: Option<&str>) -> Result<(), ClipmError> {
+        db::set_title(&self.conn, id, title)
+    }
+
+    /// Set or (with `None`) remove the trigger a text expander uses to insert
+    /// the entry, e.g. `;addr`. Each trigger belongs to at most one entry.
+    ///
+    /// ```
+    /// # use clipm::{ClipStore, ContentType};
+    /// let mut store = ClipStore::open_in_memory()?;
+    /// let id = store.add("1 Infinite Loop, Cupertino", ContentType::Text, None)?;
+    /// store.set_trigger(id, Some(";addr"))?;
+    /// assert_eq!(store.get_by_trigger(";addr")?.id, id);
+    /// assert_eq!(store.triggers()?[0].0, ";addr");
+    /// # Ok::<(), clipm::Error>(())
+    /// ```
+    pub fn set_trigger(&self, id: i64, trigger: Option<&str>) -> Result<(), ClipmError> {
+        db::set_trigger(&self.conn, id, trigger)
+    }
+
+    /// The entry with this trigger. Fails with `NotFound` if there is none.
+    pub fn get_by_trigger(&self, trigger: &str) -> Result<ClipEntry, ClipmError> {
+        db::get_by_trigger(&self.conn, trigger)
+    }
+
+    /// Every trigger with its entry, sorted by trigger.
+    pub fn triggers(&self) -> Result<Vec<(String, ClipSummary)>, ClipmError> {
+        db::triggers(&self.conn)
+    }
+
+    /// Move an entry to the trash, like `clipm delete`: it leaves history but
+    /// can be brought back with [`ClipStore::restore`] until the trash is
+    /// emptied or purged. Fails with `NotFound` for unknown IDs.
+    ///
+    /// ```
+    /// # use clipm::{ClipStore, ContentType, Error};
+    /// let mut store = ClipStore::open_in_memory()?;
+    /// let id = store.add("rm -rf target", ContentType::Text, None)?;
+    /// store.delete(id)?;
+    /// assert!(matches!(store.get(id), Err(Error::NotFound(_))));
+    /// store.restore(id)?;
+    /// assert_eq!(store.get(id)?.content, "rm -rf target");
+    /// # Ok::<(), clipm::Error>(())
+    /// ```
+    pub fn delete(&mut self, id: i64) -> Result<(), ClipmError> {
+        db::trash_many(&mut self.conn, &[id])
+    }
+
+    /// Move an entry `delete` put in the trash back into history, under its
+    /// old ID.
+    pub fn restore(&self, id: i64) -> Result<(), ClipmError> {
+        db::restore(&self.conn, id)
+    }
+
+    /// The underlying connection, for the lower-level functions in [`db`].
+    pub fn connection(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+}
@@ -0,0 +1,285 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+use crate::i18n::t;
+use crate::models::ClipmError;
+
+/// Expand `{{name}}` / `{{name:arg}}` placeholders in snippet content.
+///
+/// Supported: `{{date}}` / `{{date:FMT}}` and `{{time}}` / `{{time:FMT}}`,
+/// where `FMT` is a strftime format, and `{{counter:NAME}}`, which is replaced
+/// by the value `next_counter` returns for `NAME`. Unknown placeholders are
+/// left untouched.
+pub fn expand_placeholders<C>(
+    input: &str,
+    now: &DateTime<Local>,
+    mut next_counter: C,
+) -> Result<String, ClipmError>
+where
+    C: FnMut(&str) -> Result<i64, ClipmError>,
+{
+    render(input, |name, arg| builtin(name, arg, now, &mut next_counter))
+}
+
+/// Render a snippet for `clipm snippet use`: `{{name}}` placeholders take
+/// their value from `vars`, which may also override the built-in ones
+/// above. Fails naming every placeholder left without a value.
+pub fn render_snippet<C>(
+    input: &str,
+    vars: &BTreeMap<String, String>,
+    now: &DateTime<Local>,
+    mut next_counter: C,
+) -> Result<String, ClipmError>
+where
+    C: FnMut(&str) -> Result<i64, ClipmError>,
+{
+    let missing: Vec<String> = variables(input).into_iter().filter(|name| !vars.contains_key(name)).collect();
+    if !missing.is_empty() {
+        return Err(ClipmError::InvalidInput(t!(
+            "Missing values for {names}; pass them with --var NAME=VALUE",
+            names = missing.join(", ")
+        )));
+    }
+    render(input, |name, arg| match vars.get(name) {
+        Some(value) => Ok(Some(value.clone())),
+        None => builtin(name, arg, now, &mut next_counter),
+    })
+}
+
+/// Names of the placeholders in `input` that need a value from the user,
+/// i.e. all but `date`, `time` and `counter`, in order of first use.
+pub fn variables(input: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    // Collecting never fails: the resolver only records names
+    let _ = render(input, |name, _| {
+        if !BUILTINS.contains(&name) && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        Ok(None)
+    });
+    names
+}
+
+/// A `--var NAME=VALUE` argument.
+pub fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(t!("Invalid variable: {value}. Use NAME=VALUE.", value = s)),
+    }
+}
+
+const BUILTINS: &[&str] = &["date", "time", "counter"];
+
+fn builtin<C>(name: &str, arg: Option<&str>, now: &DateTime<Local>, next_counter: &mut C) -> Result<Option<String>, ClipmError>
+where
+    C: FnMut(&str) -> Result<i64, ClipmError>,
+{
+    match name {
+        "date" => format_time(now, arg.unwrap_or("%Y-%m-%d")).map(Some),
+        "time" => format_time(now, arg.unwrap_or("%H:%M")).map(Some),
+        "counter" => match arg.map(str::trim) {
+            Some(counter) if !counter.is_empty() => Ok(Some(next_counter(counter)?.to_string())),
+            _ => Err(ClipmError::InvalidInput(t!("Counter placeholder needs a name, e.g. {{counter:invoice}}"))),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn format_time(now: &DateTime<Local>, fmt: &str) -> Result<String, ClipmError> {
+    if StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)) {
+        return Err(ClipmError::InvalidInput(t!("Invalid date format: {format}", format = fmt)));
+    }
+    Ok(now.format(fmt).to_string())
+}
+
+/// Walk `{{...}}` tokens, asking `resolve` for a replacement. `None` keeps the
+/// token verbatim.
+fn render<F>(input: &str, mut resolve: F) -> Result<String, ClipmError>
+where
+    F: FnMut(&str, Option<&str>) -> Result<Option<String>, ClipmError>,
+{
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let token = &rest[start..start + 2 + len + 2];
+        let inner = rest[start + 2..start + 2 + len].trim();
+        let (name, arg) = match inner.split_once(':') {
+            Some((n, a)) => (n.trim(), Some(a)),
+            None => (inner, None),
+        };
+        match resolve(name, arg)? {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(token),
+        }
+        rest = &rest[start + token.len()..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Replace `$VAR` and `${VAR}` with values from the current environment.
+/// Unset variables are left untouched so literal `$` text survives.
+pub fn expand_env(input: &str) -> String {
+    expand_env_with(input, |name| std::env::var(name).ok())
+}
+
+fn expand_env_with<F: Fn(&str) -> Option<String>>(input: &str, lookup: F) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, token_len) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) if is_var_name(&braced[..end]) => (&braced[..end], end + 2),
+                _ => ("", 0),
+            }
+        } else {
+            let end = after
+                .char_indices()
+                .find(|&(i, c)| !is_var_char(c, i == 0))
+                .map(|(i, _)| i)
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[pos..pos + 1 + token_len]),
+        }
+        rest = &after[token_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_var_char(c: char, first: bool) -> bool {
+    c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+}
+
+fn is_var_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().enumerate().all(|(i, c)| is_var_char(c, i == 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/me".to_string()),
+            "USER_1" => Some("alice".to_string()),
+            _ => None,
+        }
+    }
+
+    fn no_counters(name: &str) -> Result<i64, ClipmError> {
+        panic!("unexpected counter {name}")
+    }
+
+    fn fixed_now() -> DateTime<Local> {
+        chrono::DateTime::parse_from_rfc3339("2026-02-17T09:05:00Z")
+            .unwrap()
+            .with_timezone(&Local)
+    }
+
+    #[test]
+    fn test_expand_placeholders_date_default_and_custom() {
+        let now = fixed_now();
+        let out = expand_placeholders("## {{date}} ({{date:%d/%m}})", &now, no_counters).unwrap();
+        assert_eq!(out, format!("## {} ({})", now.format("%Y-%m-%d"), now.format("%d/%m")));
+    }
+
+    #[test]
+    fn test_expand_placeholders_time() {
+        let now = fixed_now();
+        let out = expand_placeholders("at {{ time }} / {{time:%H:%M:%S}}", &now, no_counters).unwrap();
+        assert_eq!(out, format!("at {} / {}", now.format("%H:%M"), now.format("%H:%M:%S")));
+    }
+
+    #[test]
+    fn test_expand_placeholders_unknown_and_unclosed_untouched() {
+        let now = fixed_now();
+        let out = expand_placeholders("{{ name }} and {{date", &now, no_counters).unwrap();
+        assert_eq!(out, "{{ name }} and {{date");
+    }
+
+    #[test]
+    fn test_expand_placeholders_invalid_format() {
+        let err = expand_placeholders("{{date:%Y-%}}", &fixed_now(), no_counters).unwrap_err();
+        assert!(matches!(err, ClipmError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_expand_placeholders_counter() {
+        let mut counters = std::collections::HashMap::new();
+        let next = |name: &str| -> Result<i64, ClipmError> {
+            let value = counters.entry(name.to_string()).or_insert(0);
+            *value += 1;
+            Ok(*value)
+        };
+        let out = expand_placeholders("INV-{{counter:invoice}} INV-{{counter:invoice}} T-{{counter:ticket}}", &fixed_now(), next).unwrap();
+        assert_eq!(out, "INV-1 INV-2 T-1");
+    }
+
+    #[test]
+    fn test_expand_placeholders_counter_requires_name() {
+        let err = expand_placeholders("{{counter}}", &fixed_now(), no_counters).unwrap_err();
+        assert!(matches!(err, ClipmError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_render_snippet() {
+        let template = "Hi {{name}}, see you {{ date:%d.%m }}. {{name}} / {{team}}";
+        assert_eq!(variables(template), ["name", "team"]);
+        let mut vars = BTreeMap::new();
+        vars.insert("name".to_string(), "Ivan".to_string());
+        let err = render_snippet(template, &vars, &fixed_now(), no_counters).unwrap_err();
+        assert!(err.to_string().contains("team"));
+        vars.insert("team".to_string(), "Ops".to_string());
+        assert_eq!(render_snippet(template, &vars, &fixed_now(), no_counters).unwrap(), "Hi Ivan, see you 17.02. Ivan / Ops");
+        // A variable overrides the built-in of the same name
+        vars.insert("date".to_string(), "tomorrow".to_string());
+        assert_eq!(render_snippet("{{date}}", &vars, &fixed_now(), no_counters).unwrap(), "tomorrow");
+    }
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(parse_var("name=Ivan"), Ok(("name".to_string(), "Ivan".to_string())));
+        assert_eq!(parse_var("url=a=b"), Ok(("url".to_string(), "a=b".to_string())));
+        assert!(parse_var("=x").is_err());
+        assert!(parse_var("name").is_err());
+    }
+
+    #[test]
+    fn test_expand_env_plain() {
+        assert_eq!(expand_env_with("cd $HOME/src", lookup), "cd /home/me/src");
+    }
+
+    #[test]
+    fn test_expand_env_braced() {
+        assert_eq!(expand_env_with("${USER_1}s files", lookup), "alices files");
+    }
+
+    #[test]
+    fn test_expand_env_unset_left_untouched() {
+        assert_eq!(expand_env_with("$MISSING and ${MISSING}", lookup), "$MISSING and ${MISSING}");
+    }
+
+    #[test]
+    fn test_expand_env_non_variable_dollars() {
+        assert_eq!(expand_env_with("costs $5, ${bad-name} $", lookup), "costs $5, ${bad-name} $");
+    }
+}
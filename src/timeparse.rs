@@ -0,0 +1,132 @@
+//! Points in time and durations typed on the command line: `--since 2h`,
+//! `--until 2024-06-30`, `password_retention = "24h"`.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+/// Parse a duration like "90s", "30m", "24h", "7d" or "2w".
+pub fn parse_duration(text: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("invalid duration \"{text}\" (expected e.g. 30m, 24h, 7d)");
+    let trimmed = text.trim();
+    let (number, unit) = trimmed.split_at(trimmed.find(|c: char| !c.is_ascii_digit()).unwrap_or(trimmed.len()));
+    let n: i64 = number.parse().map_err(|_| invalid())?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    n.checked_mul(unit_seconds).and_then(chrono::Duration::try_seconds).ok_or_else(invalid)
+}
+
+/// The UTC instant a local calendar day starts (the first valid local time
+/// if midnight falls in a DST gap).
+pub fn start_of_local_day(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_time(NaiveTime::MIN);
+    (0..24)
+        .find_map(|h| (midnight + chrono::Duration::hours(h)).and_local_timezone(Local).earliest())
+        .map_or_else(|| midnight.and_utc(), |t| t.with_timezone(&Utc))
+}
+
+/// Which end of the day a bare date stands for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    /// Midnight at the start of the day (`--since`)
+    Start,
+    /// Midnight at its end, so the whole day is included (`--until`)
+    End,
+}
+
+/// A point in time: a duration back from `now` ("30m", "2h", "7d"), a local
+/// date ("2024-06-01"), a local date and time ("2024-06-01 14:30" or with
+/// `T`), or an RFC 3339 timestamp.
+pub fn parse_time(text: &str, edge: Edge, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let text = text.trim();
+    if let Ok(duration) = parse_duration(text) {
+        return Ok(now - duration);
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return match edge {
+            Edge::Start => Ok(start_of_local_day(day)),
+            Edge::End => day.succ_opt().map(start_of_local_day).ok_or_else(|| format!("invalid date \"{text}\"")),
+        };
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(text, format) {
+            return local
+                .and_local_timezone(Local)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc))
+                .ok_or_else(|| format!("\"{text}\" does not exist in the local time zone"));
+        }
+    }
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| format!("invalid time \"{text}\" (expected e.g. 2024-06-01, \"2024-06-01 14:30\" or 2h)"))
+}
+
+/// `--since` values; a bare date means the start of that day.
+pub fn parse_since(text: &str) -> Result<DateTime<Utc>, String> {
+    parse_time(text, Edge::Start, Utc::now())
+}
+
+/// `--until` values; a bare date includes the whole of that day.
+pub fn parse_until(text: &str) -> Result<DateTime<Utc>, String> {
+    parse_time(text, Edge::End, Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(chrono::Duration::seconds(90)));
+        assert_eq!(parse_duration("7d"), Ok(chrono::Duration::days(7)));
+        assert_eq!(parse_duration("2w"), Ok(chrono::Duration::weeks(2)));
+        assert!(parse_duration("24").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("-1h").is_err());
+        assert!(parse_duration("99999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_start_of_local_day() {
+        let day = NaiveDate::from_ymd_opt(2026, 2, 17).unwrap();
+        let start = start_of_local_day(day).with_timezone(&Local);
+        assert_eq!(start.date_naive(), day);
+        assert_eq!(start.format("%H:%M").to_string(), "00:00");
+    }
+
+    #[test]
+    fn test_parse_time_durations() {
+        let now = Utc::now();
+        assert_eq!(parse_time("30m", Edge::Start, now), Ok(now - chrono::Duration::minutes(30)));
+        assert_eq!(parse_time("2h", Edge::End, now), Ok(now - chrono::Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_parse_time_dates() {
+        let now = Utc::now();
+        let june_1 = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(parse_time("2024-06-01", Edge::Start, now), Ok(start_of_local_day(june_1)));
+        assert_eq!(parse_time("2024-05-31", Edge::End, now), Ok(start_of_local_day(june_1)));
+
+        let afternoon = june_1.and_hms_opt(14, 30, 0).unwrap().and_local_timezone(Local).unwrap().with_timezone(&Utc);
+        assert_eq!(parse_time("2024-06-01 14:30", Edge::Start, now), Ok(afternoon));
+        assert_eq!(parse_time("2024-06-01T14:30:00", Edge::End, now), Ok(afternoon));
+        assert_eq!(
+            parse_time("2024-06-01T12:00:00Z", Edge::Start, now),
+            Ok(june_1.and_hms_opt(12, 0, 0).unwrap().and_utc())
+        );
+    }
+
+    #[test]
+    fn test_parse_time_invalid() {
+        let now = Utc::now();
+        assert!(parse_time("yesterday", Edge::Start, now).is_err());
+        assert!(parse_time("2024-13-01", Edge::Start, now).is_err());
+        assert!(parse_time("", Edge::Start, now).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! `clipm://` URLs for `clipm url-handler`, in the x-callback-url style used
+//! by Shortcuts and other automation apps:
+//!
+//! ```text
+//! clipm://get?id=42&x-success=shortcuts://callback
+//! clipm://store?label=work&x-error=myapp://failed
+//! ```
+
+use crate::i18n::t;
+use crate::models::{ClipmError, ContentType, EntryRef};
+
+pub const SCHEME: &str = "clipm://";
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    /// Copy an entry (default: the most recent) to the clipboard
+    Get { id: Option<EntryRef> },
+    /// Store the current clipboard
+    Store { label: Option<String>, content_type: Option<ContentType> },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub action: Action,
+    /// Opened with the result (e.g. `id=43` for `store`) on success
+    pub x_success: Option<String>,
+    /// Opened with `errorCode` and `errorMessage` on failure
+    pub x_error: Option<String>,
+}
+
+pub fn parse(url: &str) -> Result<Request, ClipmError> {
+    let invalid = |reason: String| ClipmError::InvalidInput(t!("Invalid clipm URL \"{url}\": {reason}", url = url, reason = reason));
+    let rest = url
+        .get(..SCHEME.len())
+        .filter(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        .map(|_| &url[SCHEME.len()..])
+        .ok_or_else(|| invalid(t!("must start with {scheme}", scheme = SCHEME)))?;
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let mut params = Vec::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params.push((percent_decode(key), percent_decode(value)));
+    }
+    let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+
+    let action = match action.trim_end_matches('/') {
+        "get" => Action::Get { id: param("id").map(|id| id.parse()).transpose().map_err(invalid)? },
+        "store" => Action::Store {
+            label: param("label").filter(|l| !l.is_empty()),
+            content_type: param("type").map(|t| t.parse()).transpose().map_err(invalid)?,
+        },
+        other => return Err(invalid(t!("unknown action \"{action}\" (expected get or store)", action = other))),
+    };
+    let callback = |name: &str| param(name).map(|url| check_callback(&url).map(|()| url)).transpose().map_err(&invalid);
+    Ok(Request { action, x_success: callback("x-success")?, x_error: callback("x-error")? })
+}
+
+/// Callbacks are handed to `open`, and any web page can send a `clipm://`
+/// URL, so they must be `scheme://…` URLs for an app: no `file:` URLs, and
+/// nothing `open` could read as a path or an option.
+fn check_callback(url: &str) -> Result<(), String> {
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme).unwrap_or("");
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid {
+        return Err(t!("callback \"{callback}\" is not a scheme:// URL", callback = url));
+    }
+    if scheme.eq_ignore_ascii_case("file") {
+        return Err(t!("callback \"{callback}\" can't be a file URL", callback = url));
+    }
+    Ok(())
+}
+
+/// `base` with `params` appended to its query string.
+pub fn callback_url(base: &str, params: &[(&str, String)]) -> String {
+    let mut url = base.to_string();
+    for (i, (key, value)) in params.iter().enumerate() {
+        let separator = if i == 0 && !base.contains('?') { '?' } else { '&' };
+        url.push(separator);
+        url.push_str(&percent_encode(key));
+        url.push('=');
+        url.push_str(&percent_encode(value));
+    }
+    url
+}
+
+/// Decode `%XX` escapes and `+` (as a space). Malformed escapes are kept as-is.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Escape everything but RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_get() {
+        let request = parse("clipm://get?id=42&x-success=shortcuts%3A%2F%2Fdone").unwrap();
+        assert_eq!(request.action, Action::Get { id: Some(EntryRef::Id(42)) });
+        assert_eq!(request.x_success.as_deref(), Some("shortcuts://done"));
+        assert_eq!(request.x_error, None);
+        assert_eq!(parse("CLIPM://get/").unwrap().action, Action::Get { id: None });
+        assert_eq!(parse("clipm://get?id=%401").unwrap().action, Action::Get { id: Some(EntryRef::Recent(1)) });
+    }
+
+    #[test]
+    fn test_parse_store() {
+        let request = parse("clipm://store?label=meeting+notes&type=password").unwrap();
+        assert_eq!(
+            request.action,
+            Action::Store { label: Some("meeting notes".to_string()), content_type: Some(ContentType::Password) }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_urls() {
+        assert!(matches!(parse("https://get?id=1"), Err(ClipmError::InvalidInput(_))));
+        assert!(matches!(parse("clipm://delete?id=1"), Err(ClipmError::InvalidInput(_))));
+        assert!(matches!(parse("clipm://get?id=abc"), Err(ClipmError::InvalidInput(_))));
+        assert!(matches!(parse("clipm://store?type=bogus"), Err(ClipmError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_parse_rejects_unsafe_callbacks() {
+        for callback in ["file:///tmp/evil.command", "FILE://x", "-a%20Terminal", "/Applications/Terminal.app", "mailto:a@b", "1app://x", ""] {
+            let url = format!("clipm://get?x-success={callback}");
+            assert!(matches!(parse(&url), Err(ClipmError::InvalidInput(_))), "{callback}");
+        }
+        let request = parse("clipm://get?x-error=my-app.v2%3A%2F%2Ffailed").unwrap();
+        assert_eq!(request.x_error.as_deref(), Some("my-app.v2://failed"));
+    }
+
+    #[test]
+    fn test_percent_coding() {
+        assert_eq!(percent_decode("a%20b+c%zz%E2%9C%93"), "a b c%zz✓");
+        assert_eq!(percent_encode("a b/✓"), "a%20b%2F%E2%9C%93");
+    }
+
+    #[test]
+    fn test_callback_url() {
+        assert_eq!(callback_url("app://ok", &[("id", "7".into())]), "app://ok?id=7");
+        assert_eq!(
+            callback_url("app://x?src=clipm", &[("errorCode", "2".into()), ("errorMessage", "no entry".into())]),
+            "app://x?src=clipm&errorCode=2&errorMessage=no%20entry"
+        );
+        assert_eq!(callback_url("app://ok", &[]), "app://ok");
+    }
+}